@@ -1,12 +1,111 @@
 use asr::{
-    game_engine::unity::il2cpp::{Class, Image, Module, UnityPointer},
+    arrayvec::{ArrayString, ArrayVec},
+    future::next_tick,
+    game_engine::unity::{
+        il2cpp::{Class, Image, Module, UnityPointer},
+        SceneManager,
+    },
     Address, Address64, Process,
 };
 use bytemuck::{AnyBitPattern, CheckedBitPattern};
 use core::{fmt, marker::PhantomData, mem::size_of};
 use num_enum::{FromPrimitive, IntoPrimitive, TryFromPrimitive};
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, FromPrimitive)]
+use crate::{log, TICK_RATE};
+
+pub use self::ff1::{maps, Item, Job, Location, Monster, ITEMS};
+
+mod ff1;
+
+/// The Pixel Remaster games that the splitter can attach to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Game {
+    FinalFantasy1,
+    FinalFantasy2,
+    FinalFantasy3,
+    FinalFantasy4,
+    FinalFantasy5,
+    FinalFantasy6,
+}
+
+impl Game {
+    pub const ALL: [Game; 6] = [
+        Game::FinalFantasy1,
+        Game::FinalFantasy2,
+        Game::FinalFantasy3,
+        Game::FinalFantasy4,
+        Game::FinalFantasy5,
+        Game::FinalFantasy6,
+    ];
+
+    /// The executable of the game. The other games are read with the same
+    /// managers as Final Fantasy, only their process differs.
+    pub const fn process_name(self) -> &'static str {
+        match self {
+            Game::FinalFantasy1 => ff1::PROCESS_NAME,
+            Game::FinalFantasy2 => "FINAL FANTASY II.exe",
+            Game::FinalFantasy3 => "FINAL FANTASY III.exe",
+            Game::FinalFantasy4 => "FINAL FANTASY IV.exe",
+            Game::FinalFantasy5 => "FINAL FANTASY V.exe",
+            Game::FinalFantasy6 => "FINAL FANTASY VI.exe",
+        }
+    }
+
+    /// The game of a short name like `ff2`, as used in routes.
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|game| game.key() == key)
+    }
+
+    pub const fn key(self) -> &'static str {
+        match self {
+            Game::FinalFantasy1 => "ff1",
+            Game::FinalFantasy2 => "ff2",
+            Game::FinalFantasy3 => "ff3",
+            Game::FinalFantasy4 => "ff4",
+            Game::FinalFantasy5 => "ff5",
+            Game::FinalFantasy6 => "ff6",
+        }
+    }
+
+    /// Whether the game is only attached to during a marathon.
+    pub const fn is_marathon_only(self) -> bool {
+        matches!(
+            self,
+            Game::FinalFantasy3 | Game::FinalFantasy4 | Game::FinalFantasy5 | Game::FinalFantasy6
+        )
+    }
+
+    /// Whether the monsters, maps, and items of the game are known.
+    ///
+    /// The named splits only exist for those games, everything
+    /// else can only be split by id with a route.
+    pub const fn is_mapped(self) -> bool {
+        matches!(self, Game::FinalFantasy1)
+    }
+
+    pub fn monster(self, id: u32) -> Monster {
+        match self.is_mapped() {
+            true => Monster::from(id),
+            false => Monster::Unknown(id),
+        }
+    }
+
+    pub fn location(self, id: u32) -> Location {
+        match self.is_mapped() {
+            true => Location::from(id),
+            false => Location::Unknown(id),
+        }
+    }
+
+    pub fn item(self, id: u32) -> Option<Item> {
+        match self.is_mapped() {
+            true => Item::try_from_primitive(id).ok(),
+            false => None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, FromPrimitive, IntoPrimitive)]
 #[repr(u32)]
 pub enum BattleResult {
     None = 0,
@@ -19,157 +118,345 @@ pub enum BattleResult {
     Unknown = u32::MAX,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, TryFromPrimitive)]
-#[repr(u32)]
-pub enum Monster {
-    Garland = 350,
-    Pirates = 349,
-    Piscodemons = 88,
-    Astos = 348,
-    Vampire = 347,
-    Lich = 345,
-    EvilEye = 312,
-    Kraken = 343,
-    BlueDragon = 239,
-    Tiamat = 342,
-    Marilith = 344,
-    DeathEye = 197,
-    Lich2 = 338,
-    Marilith2 = 339,
-    Kraken2 = 340,
-    Tiamat2 = 341,
-    Chaos = 346,
-}
-
-#[derive(Copy, Clone, Debug, PartialEq, Eq, TryFromPrimitive)]
-#[repr(u32)]
-pub enum Location {
-    WorldMap = 1,
-    CastleCornelia = 2,
-    CorneliaThrone = 3,
-    Cornelia = 4,
-    CorneliaItemShop = 6,
-    MatoyaCave = 12,
-    Pravoka = 13,
-    Elfenheim = 22,
-    ElfenheimItemShop = 24,
-    _ElfenheimBMShop = 31,
-    ElvenCastle = 32,
-    WesternKeep = 33,
-    Melmond = 34,
-    _MelmondArmorShop = 37,
-    MelmondBMShop = 39,
-    SageCave = 40,
-    CrescentLake = 41,
-    CLItemShop = 43,
-    _CrescentLakeBMShop = 48,
-    Onrac = 52,
-    OnracItemShop = 54,
-    OasisShop = 59,
-    Gaia = 60,
-    GaiaItemShop = 62,
-    _GaiaBMShop = 67,
-    Lufenia = 70,
-    MarshCave1 = 73,
-    MarshCave3 = 75,
-    EarthCave3 = 78,
-    IceCave1 = 88,
-    IceCave2 = 91,
-    Underwater5 = 103,
-    WaterfallCave = 104,
-    MirageTower3 = 107,
-    FlyingFortress = 108,
-    ChaosShrine2 = 114,
-    ChaosShrine3 = 115,
-    AirHangar = 122,
-}
-
-#[derive(Copy, Clone, Debug, PartialEq, Eq, TryFromPrimitive, IntoPrimitive)]
-#[repr(u32)]
-pub enum Item {
-    Lute = 44,
-    Ship = 4,
-    Crown = 45,
-    CrystalEye = 46,
-    Tonic = 47,
-    MysticKey = 48,
-    Nitro = 49,
-    StarRuby = 52,
-    EarthRod = 53,
-    Canoe = 60,
-    LeviStone = 54,
-    AirShip = 3,
-    WarpCube = 57,
-    BottledFaerie = 58,
-    Oxyale = 59,
-    RosettaStone = 51,
-    Chime = 55,
-}
-
-impl super::EnumSetMember for Item {
-    fn ordinal(&self) -> Option<u8> {
-        u8::try_from(u32::from(*self)).ok()
+impl BattleResult {
+    /// Whether the party did not survive the battle.
+    pub fn is_lost(self) -> bool {
+        matches!(self, BattleResult::Lose | BattleResult::Restart)
+    }
+}
+
+const FORMATION_SLOTS: usize = 9;
+
+/// All monsters of an encounter, in the order of their slots.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Formation {
+    monsters: [Monster; FORMATION_SLOTS],
+    len: usize,
+}
+
+impl Formation {
+    /// The monster in the first slot, this identifies most boss fights.
+    pub fn lead(&self) -> Option<Monster> {
+        self.monsters().first().copied()
+    }
+
+    pub fn monsters(&self) -> &[Monster] {
+        &self.monsters[..self.len]
+    }
+
+    /// Every distinct monster together with how often it appears,
+    /// in the order of their first slot.
+    pub fn counts(&self) -> impl Iterator<Item = (Monster, usize)> + '_ {
+        let monsters = self.monsters();
+        monsters
+            .iter()
+            .enumerate()
+            .filter(|(slot, monster)| monsters[..*slot].contains(*monster) == false)
+            .map(|(_, monster)| {
+                let count = monsters.iter().filter(|m| *m == monster).count();
+                (*monster, count)
+            })
+    }
+
+    /// Whether both formations have the same monsters, in any order of the slots.
+    ///
+    /// The monsters are compared by their ids, so that this works
+    /// for monsters that are not known to the splitter as well.
+    pub fn same_monsters(&self, other: &Formation) -> bool {
+        let count = |formation: &Formation, id: u32| {
+            formation
+                .monsters()
+                .iter()
+                .filter(|monster| monster.id() == id)
+                .count()
+        };
+
+        self.len == other.len
+            && self
+                .monsters()
+                .iter()
+                .all(|monster| count(self, monster.id()) == count(other, monster.id()))
+    }
+}
+
+impl FromIterator<Monster> for Formation {
+    fn from_iter<I: IntoIterator<Item = Monster>>(iter: I) -> Self {
+        let mut formation = Self {
+            monsters: [Monster::Unknown(0); FORMATION_SLOTS],
+            len: 0,
+        };
+        for monster in iter.into_iter().take(FORMATION_SLOTS) {
+            formation.monsters[formation.len] = monster;
+            formation.len += 1;
+        }
+        formation
+    }
+}
+
+impl fmt::Display for Formation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut sep = "";
+        for (monster, count) in self.counts() {
+            f.write_str(sep)?;
+            match count {
+                1 => write!(f, "{monster}")?,
+                n => write!(f, "{monster} x{n}")?,
+            }
+            sep = ", ";
+        }
+        Ok(())
     }
 }
 
 pub struct Data<'a> {
+    game: Game,
     new_game: NewGame,
     battles: BattleData,
     items: ItemsData,
     user: UserData,
+    party: PartyData,
+    scenes: SceneManager,
     process: &'a Process,
     module: &'a Module,
     image: &'a Image,
 }
 
 impl<'a> Data<'a> {
-    pub async fn new(process: &'a Process, module: &'a Module, image: &'a Image) -> Self {
+    pub async fn new(
+        process: &'a Process,
+        game: Game,
+        profile: &'static Profile,
+        module: &'a Module,
+        image: &'a Image,
+    ) -> Self {
         Self {
-            new_game: NewGame::new(),
-            battles: BattleData::new(),
-            items: ItemsData::new(process, module, image).await,
-            user: UserData::new(),
+            game,
+            new_game: NewGame::new(profile),
+            battles: BattleData::new(profile),
+            items: ItemsData::new(process, module, image, profile).await,
+            user: UserData::new(profile),
+            party: PartyData::new(profile),
+            scenes: SceneManager::wait_attach(process).await,
             process,
             module,
             image,
         }
     }
+
+    /// Reads the values that are used more than once per tick.
+    pub fn update(&mut self) {
+        self.party.update(self.process, self.module, self.image);
+    }
+}
+
+/// The game state that the splitter reacts to, read once per tick.
+///
+/// [`Data`] reads these values from the running game, but the split
+/// logic only depends on this trait so that it can be driven by other
+/// sources as well.
+pub trait GameState {
+    /// The game that is running.
+    fn game(&self) -> Game;
+
+    /// Whether a battle is currently active.
+    fn battle_active(&self) -> bool;
+
+    /// All monsters of the current or last battle.
+    fn formation(&self) -> Option<Formation>;
+
+    /// The result of the current or last battle.
+    fn battle_result(&self) -> BattleResult;
+
+    /// The elapsed time of the current battle, in seconds.
+    fn battle_time(&self) -> f32;
+
+    /// All key items that are currently owned.
+    fn key_item_ids(&self) -> impl Iterator<Item = Item> + '_;
+
+    /// All vehicles that are currently owned.
+    fn vehicle_ids(&self) -> impl Iterator<Item = Item> + '_;
+
+    /// The id of the map the party is currently on.
+    fn map_id(&self) -> Option<u32>;
+
+    /// Whether the screen is fading out after confirming the party.
+    fn has_fade_out(&self) -> bool;
+
+    /// Whether the game is loading, i.e. the screen is faded or a scene is being loaded.
+    fn is_loading(&self) -> bool;
+
+    /// The members of the party, in the order of the formation.
+    fn party(&self) -> &Party;
+
+    /// The lead monster of the current or last battle.
+    fn encounter(&self) -> Option<Monster> {
+        self.formation()?.lead()
+    }
+
+    /// The map the party is currently on.
+    fn location(&self) -> Option<Location> {
+        self.map_id().map(|id| self.game().location(id))
+    }
+
+    /// Whether the game is on the title screen, i.e. no map is loaded.
+    ///
+    /// A failed read is not the title screen, otherwise a single bad read
+    /// during a run would look like returning to the title and loading a save.
+    fn in_title(&self) -> bool {
+        self.map_id() == Some(0)
+    }
 }
 
 impl Data<'_> {
     const ENCOUNTER_ID_INDEX: usize = 0;
 
-    pub fn battle_active(&self) -> bool {
+    /// Resolves every path, without reading anything behind them.
+    pub fn health(&self) -> Health {
+        let (process, module, image) = (self.process, self.module, self.image);
+        Health {
+            fade: self
+                .new_game
+                .fade_out_finish
+                .deref::<Address64>(process, module, image)
+                .is_ok()
+                && self
+                    .new_game
+                    .fade_in_finish
+                    .deref::<Address64>(process, module, image)
+                    .is_ok(),
+            battle: self
+                .battles
+                .active
+                .deref::<bool>(process, module, image)
+                .is_ok()
+                && self
+                    .battles
+                    .end_result
+                    .deref::<u32>(process, module, image)
+                    .is_ok()
+                && self
+                    .battles
+                    .elapsed_time
+                    .deref::<f32>(process, module, image)
+                    .is_ok(),
+            formation: self
+                .battles
+                .monster_party
+                .deref::<Pointer<Array<u32>>>(process, module, image)
+                .is_ok(),
+            key_items: self
+                .items
+                .key_items
+                .deref::<Pointer<Map<u32, Pointer<()>>>>(process, module, image)
+                .is_ok(),
+            vehicles: self
+                .items
+                .vehicles
+                .deref::<Pointer<List<Pointer<OwnedTransportationData>>>>(process, module, image)
+                .is_ok(),
+            map_id: self
+                .user
+                .map_id
+                .deref::<u32>(process, module, image)
+                .is_ok(),
+            // The field names of the party members are not verified yet,
+            // so their values also have to look like a party
+            party: self.party.offsets.is_some()
+                && self
+                    .party
+                    .characters
+                    .deref::<Pointer<List<Pointer<()>>>>(process, module, image)
+                    .is_ok()
+                && self.party.members.iter().all(Character::is_plausible),
+        }
+    }
+}
+
+/// Which parts of the game state can currently be read.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Health {
+    pub fade: bool,
+    pub battle: bool,
+    pub formation: bool,
+    pub key_items: bool,
+    pub vehicles: bool,
+    pub map_id: bool,
+    pub party: bool,
+}
+
+impl fmt::Display for Health {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts = [
+            ("fade", self.fade),
+            ("battle", self.battle),
+            ("formation", self.formation),
+            ("key items", self.key_items),
+            ("vehicles", self.vehicles),
+            ("map id", self.map_id),
+            ("party", self.party),
+        ];
+
+        let mut sep = "";
+        for (name, ok) in parts {
+            let status = if ok { "OK" } else { "FAILED" };
+            write!(f, "{sep}{name} {status}")?;
+            sep = " / ";
+        }
+        Ok(())
+    }
+}
+
+impl GameState for Data<'_> {
+    fn game(&self) -> Game {
+        self.game
+    }
+
+    fn battle_active(&self) -> bool {
         self.battles
             .active
             .deref(self.process, self.module, self.image)
             .unwrap_or_default()
     }
 
-    pub fn encounter(&self) -> Option<Monster> {
+    // Only reads the first slot, this is checked every tick during battles
+    fn encounter(&self) -> Option<Monster> {
         self.battles
             .monster_party
-            .deref::<Pointer<Array<_>>>(self.process, self.module, self.image)
+            .deref::<Pointer<Array<u32>>>(self.process, self.module, self.image)
             .ok()?
             .get(self.process, Self::ENCOUNTER_ID_INDEX)
-            .and_then(|id| Monster::try_from_primitive(id).ok())
+            .map(|id| self.game.monster(id))
     }
 
-    pub fn battle_result(&self) -> BattleResult {
+    fn formation(&self) -> Option<Formation> {
+        let formation = self
+            .battles
+            .monster_party
+            .deref::<Pointer<Array<u32>>>(self.process, self.module, self.image)
+            .ok()?
+            .iter(self.process)?
+            // empty slots are 0
+            .filter(|id| *id != 0)
+            .map(|id| self.game.monster(id))
+            .collect();
+        Some(formation)
+    }
+
+    fn battle_result(&self) -> BattleResult {
         self.battles
             .end_result
             .deref::<u32>(self.process, self.module, self.image)
             .map_or(BattleResult::Unknown, BattleResult::from)
     }
 
-    pub fn battle_time(&self) -> f32 {
+    fn battle_time(&self) -> f32 {
         self.battles
             .elapsed_time
             .deref(self.process, self.module, self.image)
             .unwrap_or_default()
     }
 
-    pub fn key_item_ids(&self) -> impl Iterator<Item = Item> + '_ {
+    fn key_item_ids(&self) -> impl Iterator<Item = Item> + '_ {
         self.items
             .key_items
             .deref::<Pointer<Map<u32, Pointer<()>>>>(self.process, self.module, self.image)
@@ -177,10 +464,10 @@ impl Data<'_> {
             .filter_map(|key_items| key_items.iter(self.process))
             .flatten()
             .map(|(item_id_plus_1, _)| item_id_plus_1 - 1)
-            .filter_map(|item_id| Item::try_from_primitive(item_id).ok())
+            .filter_map(|item_id| self.game.item(item_id))
     }
 
-    pub fn vehicle_ids(&self) -> impl Iterator<Item = Item> + '_ {
+    fn vehicle_ids(&self) -> impl Iterator<Item = Item> + '_ {
         self.items
             .vehicles
             .deref::<Pointer<List<Pointer<OwnedTransportationData>>>>(
@@ -203,41 +490,176 @@ impl Data<'_> {
                     .read(self.process, vehicle.data.addr())
                     .ok()?;
 
-                let item = Item::try_from(vehicle.id).ok()?;
+                let item = self.game.item(vehicle.id)?;
                 let _ = u32::try_from(vehicle.map_id).ok()?;
 
                 Some(item)
             })
     }
 
-    pub fn location(&self) -> Option<Location> {
+    fn map_id(&self) -> Option<u32> {
         self.user
             .map_id
             .deref(self.process, self.module, self.image)
             .ok()
-            .and_then(|id| Location::try_from_primitive(id).ok())
     }
 
-    pub fn has_fade_out(&self) -> bool {
+    fn has_fade_out(&self) -> bool {
         self.new_game
             .has_fade_out(self.process, self.module, self.image)
             .unwrap_or(false)
     }
+
+    fn is_loading(&self) -> bool {
+        let fading = self
+            .new_game
+            .is_fading(self.process, self.module, self.image)
+            .unwrap_or(false);
+
+        // Scenes are loaded additively, there is more than one
+        // scene only while the next one is loading.
+        let loading_scene = self
+            .scenes
+            .get_scene_count(self.process)
+            .is_ok_and(|count| count > 1);
+
+        fading || loading_scene
+    }
+
+    fn party(&self) -> &Party {
+        &self.party.members
+    }
 }
 
 fn ptr_path<const N: usize>(cls: &'static str, path: [&'static str; N]) -> UnityPointer<N> {
     UnityPointer::new(cls, 0, &path)
 }
 
+/// The names of the fields that are read, for one build of the game.
+///
+/// Updates of the game can rename fields, every build that
+/// renames one of them needs its own profile.
+pub struct Profile {
+    pub name: &'static str,
+    fade_out_finish: [&'static str; 2],
+    fade_in_finish: [&'static str; 2],
+    battle_active: [&'static str; 2],
+    monster_party: [&'static str; 5],
+    end_result: [&'static str; 3],
+    elapsed_time: [&'static str; 2],
+    key_items: [&'static str; 2],
+    vehicles: [&'static str; 2],
+    map_id: [&'static str; 2],
+    /// Not part of the detection, the party is optional.
+    party: [&'static str; 2],
+}
+
+const PROFILES: &[Profile] = &[Profile {
+    name: "Release",
+    fade_out_finish: ["instance", "fadeOutFinishedCallback"],
+    fade_in_finish: ["instance", "fadeInFinishedCallback"],
+    battle_active: ["instance", "isBattle"],
+    monster_party: [
+        "instance",
+        "<InstantiateManager>k__BackingField",
+        "<battleEnemyInstanceData>k__BackingField",
+        "<monsterParty>k__BackingField",
+        "valueIntList",
+    ],
+    end_result: [
+        "instance",
+        "<BattleEndJugment>k__BackingField",
+        "resultType",
+    ],
+    elapsed_time: ["instance", "elapsedTime"],
+    key_items: ["instance", "importantOwendItems"],
+    vehicles: ["instance", "<OwnedTransportationList>k__BackingField"],
+    map_id: ["instance", "<CurrentMapId>k__BackingField"],
+    party: ["instance", "<OwnedCharacterList>k__BackingField"],
+}];
+
+/// How long to wait for the managers to be created, one minute.
+const DETECT_TICKS: u32 = TICK_RATE * 60;
+
+impl Profile {
+    /// Finds the profile whose fields exist in the attached build.
+    ///
+    /// The builds are told apart by their fields instead of the size of
+    /// `GameAssembly.dll`. Only the release build is supported so far and
+    /// the sizes of other builds are not known, while the fields are
+    /// exactly what has to exist for the pointer paths.
+    /// The classes are only there once the game has set up its managers,
+    /// so this waits for a while before giving up.
+    pub async fn detect(
+        process: &Process,
+        module: &Module,
+        image: &Image,
+    ) -> Option<&'static Self> {
+        for _ in 0..DETECT_TICKS {
+            let profile = PROFILES
+                .iter()
+                .find(|profile| profile.missing(process, module, image).is_none());
+            if profile.is_some() {
+                return profile;
+            }
+            next_tick().await;
+        }
+
+        for profile in PROFILES {
+            if let Some((class, field)) = profile.missing(process, module, image) {
+                log!(
+                    "Missing field {class}.{field} for the {} profile",
+                    profile.name
+                );
+            }
+        }
+        return None;
+    }
+
+    /// The first field that does not exist in the attached build.
+    ///
+    /// Only the fields on the managers are checked, the classes
+    /// behind them are only known once the game created them.
+    fn missing(
+        &self,
+        process: &Process,
+        module: &Module,
+        image: &Image,
+    ) -> Option<(&'static str, &'static str)> {
+        let fields = [
+            ("FadeManager", self.fade_out_finish[1]),
+            ("FadeManager", self.fade_in_finish[1]),
+            ("BattlePlugManager", self.battle_active[1]),
+            ("BattlePlugManager", self.monster_party[1]),
+            ("BattlePlugManager", self.end_result[1]),
+            ("BattlePlugManager", self.elapsed_time[1]),
+            ("UserDataManager", self.key_items[1]),
+            ("UserDataManager", self.vehicles[1]),
+            ("UserDataManager", self.map_id[1]),
+        ];
+
+        fields.into_iter().find(|&(class, field)| {
+            image
+                .get_class(process, module, class)
+                .and_then(|class| class.get_field_offset(process, module, field))
+                .is_none()
+        })
+    }
+}
+
 struct NewGame {
     fade_out_finish: UnityPointer<2>,
+    fade_in_finish: UnityPointer<2>,
 }
 
 impl NewGame {
-    fn new() -> Self {
-        let fade_out_finish =
-            UnityPointer::new("FadeManager", 1, &["instance", "fadeOutFinishedCallback"]);
-        Self { fade_out_finish }
+    fn new(profile: &'static Profile) -> Self {
+        let fade_out_finish = UnityPointer::new("FadeManager", 1, &profile.fade_out_finish);
+        let fade_in_finish = UnityPointer::new("FadeManager", 1, &profile.fade_in_finish);
+        Self {
+            fade_out_finish,
+            fade_in_finish,
+        }
     }
 
     fn has_fade_out(&self, process: &Process, module: &Module, image: &Image) -> Option<bool> {
@@ -247,6 +669,15 @@ impl NewGame {
             .ok()?;
         Some(ptr.is_null() == false)
     }
+
+    fn is_fading(&self, process: &Process, module: &Module, image: &Image) -> Option<bool> {
+        // The callbacks are only set while a fade is in progress
+        let fade_in = self
+            .fade_in_finish
+            .deref::<Address64>(process, module, image)
+            .ok()?;
+        Some(self.has_fade_out(process, module, image)? || fade_in.is_null() == false)
+    }
 }
 
 struct BattleData {
@@ -257,27 +688,11 @@ struct BattleData {
 }
 
 impl BattleData {
-    fn new() -> Self {
-        let active = ptr_path("BattlePlugManager", ["instance", "isBattle"]);
-        let monster_party = ptr_path(
-            "BattlePlugManager",
-            [
-                "instance",
-                "<InstantiateManager>k__BackingField",
-                "<battleEnemyInstanceData>k__BackingField",
-                "<monsterParty>k__BackingField",
-                "valueIntList",
-            ],
-        );
-        let end_result = ptr_path(
-            "BattlePlugManager",
-            [
-                "instance",
-                "<BattleEndJugment>k__BackingField",
-                "resultType",
-            ],
-        );
-        let elapsed_time = ptr_path("BattlePlugManager", ["instance", "elapsedTime"]);
+    fn new(profile: &'static Profile) -> Self {
+        let active = ptr_path("BattlePlugManager", profile.battle_active);
+        let monster_party = ptr_path("BattlePlugManager", profile.monster_party);
+        let end_result = ptr_path("BattlePlugManager", profile.end_result);
+        let elapsed_time = ptr_path("BattlePlugManager", profile.elapsed_time);
 
         Self {
             active,
@@ -309,12 +724,14 @@ struct ItemsData {
 }
 
 impl ItemsData {
-    async fn new(process: &Process, module: &Module, image: &Image) -> Self {
-        let key_items = ptr_path("UserDataManager", ["instance", "importantOwendItems"]);
-        let vehicles = ptr_path(
-            "UserDataManager",
-            ["instance", "<OwnedTransportationList>k__BackingField"],
-        );
+    async fn new(
+        process: &Process,
+        module: &Module,
+        image: &Image,
+        profile: &'static Profile,
+    ) -> Self {
+        let key_items = ptr_path("UserDataManager", profile.key_items);
+        let vehicles = ptr_path("UserDataManager", profile.vehicles);
 
         let transport_data = OwnedTransportationData::bind(process, module, image).await;
         let save_transport = SaveTransportationData::bind(process, module, image).await;
@@ -333,16 +750,217 @@ struct UserData {
 }
 
 impl UserData {
-    fn new() -> Self {
-        let map_id = ptr_path(
-            "UserDataManager",
-            ["instance", "<CurrentMapId>k__BackingField"],
-        );
+    fn new(profile: &'static Profile) -> Self {
+        let map_id = ptr_path("UserDataManager", profile.map_id);
 
         Self { map_id }
     }
 }
 
+pub const PARTY_SIZE: usize = 4;
+
+pub type Party = ArrayVec<Character, PARTY_SIZE>;
+
+/// A member of the party.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Character {
+    pub name: ArrayString<32>,
+    pub job_id: u32,
+    pub level: u32,
+    pub exp: u32,
+    pub hp: u32,
+    pub max_hp: u32,
+    pub mp: u32,
+    pub max_mp: u32,
+}
+
+impl Character {
+    pub fn job(&self) -> Option<Job> {
+        Job::try_from_primitive(self.job_id).ok()
+    }
+
+    /// Whether the values are in the range of a party member.
+    fn is_plausible(&self) -> bool {
+        (1..=99).contains(&self.level) && self.max_hp > 0 && self.hp <= self.max_hp
+    }
+}
+
+impl fmt::Display for Character {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (job {}) Lv {}, {} exp, HP {}/{}, MP {}/{}",
+            self.name,
+            self.job_id,
+            self.level,
+            self.exp,
+            self.hp,
+            self.max_hp,
+            self.mp,
+            self.max_mp,
+        )
+    }
+}
+
+/// Where the values of a party member are, within their classes.
+///
+/// The field names have not been verified against the game yet. Whether
+/// they resolve and read values that look right is reported as the
+/// `party` part of the [`Health`].
+struct CharacterOffsets {
+    name: u32,
+    job_id: u32,
+    parameter: u32,
+    level: u32,
+    exp: u32,
+    hp: u32,
+    max_hp: u32,
+    mp: u32,
+    max_mp: u32,
+}
+
+impl CharacterOffsets {
+    fn resolve(process: &Process, module: &Module, image: &Image) -> Option<Self> {
+        let character = image.get_class(process, module, "OwnedCharacterData")?;
+        let parameter = image.get_class(process, module, "OwnedCharacterParameter")?;
+        let field = |class: Class, name: &str| field_offset(process, module, class, name);
+
+        Some(Self {
+            name: field(character, "name")?,
+            job_id: field(character, "jobId")?,
+            parameter: field(character, "parameter")?,
+            level: field(parameter, "BaseLevel")?,
+            exp: field(parameter, "currentExp")?,
+            hp: field(parameter, "currentHP")?,
+            max_hp: field(parameter, "BaseMaxHp")?,
+            mp: field(parameter, "currentMP")?,
+            max_mp: field(parameter, "BaseMaxMp")?,
+        })
+    }
+}
+
+/// Finds the offset of a field in the class or any of its parents.
+fn field_offset(process: &Process, module: &Module, class: Class, name: &str) -> Option<u32> {
+    let mut class = Some(class);
+    while let Some(current) = class {
+        if let Some(offset) = current.get_field_offset(process, module, name) {
+            return Some(offset);
+        }
+        class = current.get_parent(process, module);
+    }
+    None
+}
+
+/// How often to look for the classes of the party members again, every 5 seconds.
+const RESOLVE_TICKS: u32 = TICK_RATE * 5;
+
+struct PartyData {
+    characters: UnityPointer<2>,
+    offsets: Option<CharacterOffsets>,
+    resolve_in: u32,
+    members: Party,
+}
+
+impl PartyData {
+    // The classes are only created once the game loaded the party, they are
+    // looked up again until they are found. Until then, the party is empty
+    // and everything else still splits.
+    fn new(profile: &'static Profile) -> Self {
+        Self {
+            characters: ptr_path("UserDataManager", profile.party),
+            offsets: None,
+            resolve_in: 0,
+            members: Party::new(),
+        }
+    }
+
+    fn update(&mut self, process: &Process, module: &Module, image: &Image) {
+        if self.offsets.is_none() {
+            if self.resolve_in > 0 {
+                self.resolve_in -= 1;
+                return;
+            }
+            self.offsets = CharacterOffsets::resolve(process, module, image);
+            if self.offsets.is_none() {
+                self.resolve_in = RESOLVE_TICKS;
+                return;
+            }
+            log!("Found the party members");
+        }
+
+        self.members = self
+            .characters
+            .deref::<Pointer<List<Pointer<()>>>>(process, module, image)
+            .into_iter()
+            .filter_map(|characters| characters.iter(process))
+            .flatten()
+            .filter_map(|character| self.read(process, character))
+            .take(PARTY_SIZE)
+            .collect();
+    }
+
+    fn read(&self, process: &Process, character: Pointer<()>) -> Option<Character> {
+        let offsets = self.offsets.as_ref()?;
+        if character.address().is_null() {
+            return None;
+        }
+        let character = character.addr();
+        let parameter = process
+            .read::<Address64>(character + u64::from(offsets.parameter))
+            .ok()?;
+        if parameter.is_null() {
+            return None;
+        }
+        let parameter = Address::from(parameter);
+
+        let value = |address: Address, offset: u32| {
+            let value = process.read::<i32>(address + u64::from(offset)).ok()?;
+            u32::try_from(value).ok()
+        };
+
+        Some(Character {
+            name: read_string(process, character + u64::from(offsets.name))?,
+            job_id: value(character, offsets.job_id)?,
+            level: value(parameter, offsets.level)?,
+            exp: value(parameter, offsets.exp)?,
+            hp: value(parameter, offsets.hp)?,
+            max_hp: value(parameter, offsets.max_hp)?,
+            mp: value(parameter, offsets.mp)?,
+            max_mp: value(parameter, offsets.max_mp)?,
+        })
+    }
+}
+
+/// Reads a `System.String`, that the field at the address points to.
+///
+/// Names that are too long are cut off.
+fn read_string<const N: usize>(process: &Process, field: Address) -> Option<ArrayString<N>> {
+    const LENGTH: u64 = 0x10;
+    const CHARS: u64 = 0x14;
+
+    let string = process.read::<Address64>(field).ok()?;
+    if string.is_null() {
+        return None;
+    }
+    let string = Address::from(string);
+
+    let length = process.read::<i32>(string + LENGTH).ok()?;
+    let mut chars = [0_u16; N];
+    let chars = &mut chars[..usize::try_from(length).ok()?.min(N)];
+    process.read_into_slice(string + CHARS, chars).ok()?;
+
+    let mut text = ArrayString::new();
+    for c in char::decode_utf16(chars.iter().copied()) {
+        if text
+            .try_push(c.unwrap_or(char::REPLACEMENT_CHARACTER))
+            .is_err()
+        {
+            break;
+        }
+    }
+    Some(text)
+}
+
 /// Trait for things that can read data from memory.
 trait MemReader: Sized {
     /// Reads a value from memory.
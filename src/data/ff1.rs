@@ -0,0 +1,403 @@
+//! The monsters, maps, and items of Final Fantasy.
+
+use core::fmt;
+use num_enum::{FromPrimitive, IntoPrimitive, TryFromPrimitive};
+
+use crate::EnumSetMember;
+
+pub const PROCESS_NAME: &str = "FINAL FANTASY.exe";
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, FromPrimitive, IntoPrimitive)]
+#[repr(u32)]
+pub enum Monster {
+    Garland = 350,
+    Pirates = 349,
+    Piscodemons = 88,
+    Astos = 348,
+    Vampire = 347,
+    Lich = 345,
+    EvilEye = 312,
+    Kraken = 343,
+    BlueDragon = 239,
+    Tiamat = 342,
+    Marilith = 344,
+    DeathEye = 197,
+    Lich2 = 338,
+    Marilith2 = 339,
+    Kraken2 = 340,
+    Tiamat2 = 341,
+    Chaos = 346,
+    #[num_enum(catch_all)]
+    Unknown(u32),
+}
+
+/// The lead monsters of the boss fights.
+///
+/// The optional fights belong in here as well once their ids are
+/// confirmed in game, e.g. WarMECH.
+const BOSSES: &[Monster] = &[
+    Monster::Garland,
+    Monster::Pirates,
+    Monster::Piscodemons,
+    Monster::Astos,
+    Monster::Vampire,
+    Monster::Lich,
+    Monster::EvilEye,
+    Monster::Kraken,
+    Monster::BlueDragon,
+    Monster::Tiamat,
+    Monster::Marilith,
+    Monster::DeathEye,
+    Monster::Lich2,
+    Monster::Marilith2,
+    Monster::Kraken2,
+    Monster::Tiamat2,
+    Monster::Chaos,
+];
+
+impl Monster {
+    pub fn id(self) -> u32 {
+        u32::from(self)
+    }
+
+    /// Whether this is a boss fight, any other encounter is a regular,
+    /// random encounter.
+    ///
+    /// This only depends on [`BOSSES`], a boss does not need a name.
+    pub fn is_boss(self) -> bool {
+        BOSSES.contains(&self)
+    }
+
+    pub fn name(self) -> Option<&'static str> {
+        let name = match self {
+            Monster::Garland => "Garland",
+            Monster::Pirates => "Pirates",
+            Monster::Piscodemons => "Piscodemons",
+            Monster::Astos => "Astos",
+            Monster::Vampire => "Vampire",
+            Monster::Lich => "Lich",
+            Monster::EvilEye => "Evil Eye",
+            Monster::Kraken => "Kraken",
+            Monster::BlueDragon => "Blue Dragon",
+            Monster::Tiamat => "Tiamat",
+            Monster::Marilith => "Marilith",
+            Monster::DeathEye => "Death Eye",
+            Monster::Lich2 => "Lich (Chaos Shrine)",
+            Monster::Marilith2 => "Marilith (Chaos Shrine)",
+            Monster::Kraken2 => "Kraken (Chaos Shrine)",
+            Monster::Tiamat2 => "Tiamat (Chaos Shrine)",
+            Monster::Chaos => "Chaos",
+            Monster::Unknown(_) => return None,
+        };
+        Some(name)
+    }
+}
+
+impl fmt::Display for Monster {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => f.write_str(name),
+            None => write!(f, "{}", self.id()),
+        }
+    }
+}
+#[derive(Copy, Clone, Debug, PartialEq, Eq, FromPrimitive, IntoPrimitive)]
+#[repr(u32)]
+pub enum Location {
+    WorldMap = 1,
+    CastleCornelia = 2,
+    CorneliaThrone = 3,
+    Cornelia = 4,
+    CorneliaItemShop = 6,
+    MatoyaCave = 12,
+    Pravoka = 13,
+    Elfenheim = 22,
+    ElfenheimItemShop = 24,
+    _ElfenheimBMShop = 31,
+    ElvenCastle = 32,
+    WesternKeep = 33,
+    Melmond = 34,
+    _MelmondArmorShop = 37,
+    MelmondBMShop = 39,
+    SageCave = 40,
+    CrescentLake = 41,
+    CLItemShop = 43,
+    _CrescentLakeBMShop = 48,
+    Onrac = 52,
+    OnracItemShop = 54,
+    OasisShop = 59,
+    Gaia = 60,
+    GaiaItemShop = 62,
+    _GaiaBMShop = 67,
+    Lufenia = 70,
+    MarshCave1 = 73,
+    MarshCave3 = 75,
+    EarthCave3 = 78,
+    IceCave1 = 88,
+    IceCave2 = 91,
+    Underwater5 = 103,
+    WaterfallCave = 104,
+    MirageTower3 = 107,
+    FlyingFortress = 108,
+    ChaosShrine2 = 114,
+    ChaosShrine3 = 115,
+    AirHangar = 122,
+    #[num_enum(catch_all)]
+    Unknown(u32),
+}
+
+/// Every map id of the known areas, ordered by their id.
+///
+/// This includes the maps that have no name yet, e.g. most shops.
+pub fn maps() -> impl Iterator<Item = Location> {
+    AREAS
+        .iter()
+        .flat_map(|area| area.first..=area.last)
+        .map(Location::from)
+}
+
+/// The ordinal is the map id, only the maps of the known areas are members.
+impl EnumSetMember for Location {
+    fn ordinal(&self) -> Option<u16> {
+        self.area()?;
+        u16::try_from(self.id()).ok()
+    }
+
+    fn from_ordinal(ordinal: u16) -> Option<Self> {
+        let location = Self::from(u32::from(ordinal));
+        location.area()?;
+        Some(location)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MapKind {
+    WorldMap,
+    Town,
+    Shop,
+    Dungeon,
+    Vehicle,
+}
+
+/// A range of consecutive map ids that belong to the same area.
+#[derive(Copy, Clone, Debug)]
+pub struct Area {
+    pub first: u32,
+    pub last: u32,
+    pub name: &'static str,
+    pub kind: MapKind,
+}
+
+impl Area {
+    const fn new(first: u32, last: u32, name: &'static str, kind: MapKind) -> Self {
+        Self {
+            first,
+            last,
+            name,
+            kind,
+        }
+    }
+}
+
+/// The map ids that are known so far, grouped by area.
+///
+/// Towns are followed by the maps of their buildings,
+/// dungeons are numbered from their first floor upwards.
+///
+/// This is not the complete table yet. The ids 49–51, 68–69, 71–72, 81–87,
+/// 92–98, 116–121, and everything above 122 have not been matched to a map
+/// in the game. Among them are Gurgu Volcano, the Citadel of Trials, the
+/// Mermaid floors of the Sunken Shrine, and the past floors of the Chaos
+/// Shrine. Those maps stay [`Location::Unknown`] and are shown by their id
+/// until they are confirmed in game.
+pub const AREAS: &[Area] = &[
+    Area::new(1, 1, "World Map", MapKind::WorldMap),
+    Area::new(2, 3, "Castle Cornelia", MapKind::Town),
+    Area::new(4, 4, "Cornelia", MapKind::Town),
+    Area::new(5, 11, "Cornelia", MapKind::Shop),
+    Area::new(12, 12, "Matoya's Cave", MapKind::Town),
+    Area::new(13, 13, "Pravoka", MapKind::Town),
+    Area::new(14, 21, "Pravoka", MapKind::Shop),
+    Area::new(22, 22, "Elfenheim", MapKind::Town),
+    Area::new(23, 31, "Elfenheim", MapKind::Shop),
+    Area::new(32, 32, "Elven Castle", MapKind::Town),
+    Area::new(33, 33, "Western Keep", MapKind::Dungeon),
+    Area::new(34, 34, "Melmond", MapKind::Town),
+    Area::new(35, 39, "Melmond", MapKind::Shop),
+    Area::new(40, 40, "Sage's Cave", MapKind::Town),
+    Area::new(41, 41, "Crescent Lake", MapKind::Town),
+    Area::new(42, 48, "Crescent Lake", MapKind::Shop),
+    Area::new(52, 52, "Onrac", MapKind::Town),
+    Area::new(53, 58, "Onrac", MapKind::Shop),
+    Area::new(59, 59, "Oasis", MapKind::Shop),
+    Area::new(60, 60, "Gaia", MapKind::Town),
+    Area::new(61, 67, "Gaia", MapKind::Shop),
+    Area::new(70, 70, "Lufenia", MapKind::Town),
+    Area::new(73, 75, "Marsh Cave", MapKind::Dungeon),
+    Area::new(76, 80, "Earth Cave", MapKind::Dungeon),
+    Area::new(88, 91, "Ice Cave", MapKind::Dungeon),
+    Area::new(99, 103, "Sunken Shrine", MapKind::Dungeon),
+    Area::new(104, 104, "Waterfall Cave", MapKind::Dungeon),
+    Area::new(105, 107, "Mirage Tower", MapKind::Dungeon),
+    Area::new(108, 112, "Flying Fortress", MapKind::Dungeon),
+    Area::new(113, 115, "Chaos Shrine", MapKind::Dungeon),
+    Area::new(122, 122, "Air Ship Hangar", MapKind::Vehicle),
+];
+
+impl Location {
+    pub fn id(self) -> u32 {
+        u32::from(self)
+    }
+
+    /// The area this map belongs to, if it is known.
+    pub fn area(self) -> Option<&'static Area> {
+        let id = self.id();
+        AREAS
+            .iter()
+            .find(|area| (area.first..=area.last).contains(&id))
+    }
+
+    pub fn kind(self) -> Option<MapKind> {
+        self.area().map(|area| area.kind)
+    }
+
+    pub fn name(self) -> Option<&'static str> {
+        let name = match self {
+            Location::WorldMap => "World Map",
+            Location::CastleCornelia => "Castle Cornelia",
+            Location::CorneliaThrone => "Castle Cornelia Throne Room",
+            Location::Cornelia => "Cornelia",
+            Location::CorneliaItemShop => "Cornelia Item Shop",
+            Location::MatoyaCave => "Matoya's Cave",
+            Location::Pravoka => "Pravoka",
+            Location::Elfenheim => "Elfenheim",
+            Location::ElfenheimItemShop => "Elfenheim Item Shop",
+            Location::_ElfenheimBMShop => "Elfenheim Black Magic Shop",
+            Location::ElvenCastle => "Elven Castle",
+            Location::WesternKeep => "Western Keep",
+            Location::Melmond => "Melmond",
+            Location::_MelmondArmorShop => "Melmond Armor Shop",
+            Location::MelmondBMShop => "Melmond Black Magic Shop",
+            Location::SageCave => "Sage's Cave",
+            Location::CrescentLake => "Crescent Lake",
+            Location::CLItemShop => "Crescent Lake Item Shop",
+            Location::_CrescentLakeBMShop => "Crescent Lake Black Magic Shop",
+            Location::Onrac => "Onrac",
+            Location::OnracItemShop => "Onrac Item Shop",
+            Location::OasisShop => "Oasis Shop",
+            Location::Gaia => "Gaia",
+            Location::GaiaItemShop => "Gaia Item Shop",
+            Location::_GaiaBMShop => "Gaia Black Magic Shop",
+            Location::Lufenia => "Lufenia",
+            Location::MarshCave1 => "Marsh Cave B1",
+            Location::MarshCave3 => "Marsh Cave B3",
+            Location::EarthCave3 => "Earth Cave B3",
+            Location::IceCave1 => "Ice Cave B1",
+            Location::IceCave2 => "Ice Cave B2",
+            Location::Underwater5 => "Sunken Shrine 5F",
+            Location::WaterfallCave => "Waterfall Cave",
+            Location::MirageTower3 => "Mirage Tower 3F",
+            Location::FlyingFortress => "Flying Fortress",
+            Location::ChaosShrine2 => "Chaos Shrine 2F",
+            Location::ChaosShrine3 => "Chaos Shrine 3F",
+            Location::AirHangar => "Air Ship Hangar",
+            Location::Unknown(_) => return self.area().map(|area| area.name),
+        };
+        Some(name)
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.name(), self) {
+            (Some(name), Location::Unknown(id)) if self.kind() == Some(MapKind::Shop) => {
+                write!(f, "{name} Shop ({id})")
+            }
+            (Some(name), Location::Unknown(id)) => write!(f, "{name} ({id})"),
+            (Some(name), _) => f.write_str(name),
+            (None, _) => write!(f, "{}", self.id()),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, TryFromPrimitive, IntoPrimitive)]
+#[repr(u32)]
+pub enum Item {
+    Lute = 44,
+    Ship = 4,
+    Crown = 45,
+    CrystalEye = 46,
+    Tonic = 47,
+    MysticKey = 48,
+    Nitro = 49,
+    Adamantite = 50,
+    StarRuby = 52,
+    EarthRod = 53,
+    Canoe = 60,
+    LeviStone = 54,
+    AirShip = 3,
+    RatsTail = 56,
+    WarpCube = 57,
+    BottledFaerie = 58,
+    Oxyale = 59,
+    RosettaStone = 51,
+    Chime = 55,
+}
+
+/// All key items and vehicles, ordered by their id.
+pub const ITEMS: &[Item] = &[
+    Item::AirShip,
+    Item::Ship,
+    Item::Lute,
+    Item::Crown,
+    Item::CrystalEye,
+    Item::Tonic,
+    Item::MysticKey,
+    Item::Nitro,
+    Item::Adamantite,
+    Item::RosettaStone,
+    Item::StarRuby,
+    Item::EarthRod,
+    Item::LeviStone,
+    Item::Chime,
+    Item::RatsTail,
+    Item::WarpCube,
+    Item::BottledFaerie,
+    Item::Oxyale,
+    Item::Canoe,
+];
+
+impl EnumSetMember for Item {
+    fn ordinal(&self) -> Option<u16> {
+        u16::try_from(u32::from(*self)).ok()
+    }
+
+    fn from_ordinal(ordinal: u16) -> Option<Self> {
+        Self::try_from_primitive(u32::from(ordinal)).ok()
+    }
+}
+
+/// The jobs of the party, the class change turns every
+/// job into the advanced job 6 ids further.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, TryFromPrimitive, IntoPrimitive)]
+#[repr(u32)]
+pub enum Job {
+    Warrior = 1,
+    Thief = 2,
+    Monk = 3,
+    RedMage = 4,
+    WhiteMage = 5,
+    BlackMage = 6,
+    Knight = 7,
+    Ninja = 8,
+    Master = 9,
+    RedWizard = 10,
+    WhiteWizard = 11,
+    BlackWizard = 12,
+}
+
+impl Job {
+    pub fn is_advanced(self) -> bool {
+        u32::from(self) > u32::from(Job::BlackMage)
+    }
+}
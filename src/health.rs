@@ -0,0 +1,48 @@
+//! Regular checks that every part of the game state can be read.
+//!
+//! A path that does not resolve anymore only shows up as splits that
+//! never happen, so the result is logged and published as the `Pointers`
+//! timer variable whenever it changes.
+
+use asr::{arrayvec::ArrayString, timer};
+use core::fmt::Write;
+
+use crate::{
+    data::{Data, Health},
+    log, TICK_RATE,
+};
+
+/// Every 5 seconds.
+const INTERVAL: u32 = TICK_RATE * 5;
+
+pub struct HealthCheck {
+    ticks: u32,
+    last: Option<Health>,
+}
+
+impl HealthCheck {
+    pub fn new() -> Self {
+        Self {
+            ticks: 0,
+            last: None,
+        }
+    }
+
+    /// Checks right away on the first call, afterwards only every few seconds.
+    pub fn update(&mut self, data: &Data<'_>) {
+        if self.ticks > 0 {
+            self.ticks -= 1;
+            return;
+        }
+        self.ticks = INTERVAL;
+
+        let health = data.health();
+        if self.last.replace(health) != Some(health) {
+            log!("Pointers: {health}");
+
+            let mut status = ArrayString::<128>::new();
+            let _ = write!(status, "{health}");
+            timer::set_variable("Pointers", &status);
+        }
+    }
+}
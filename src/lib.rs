@@ -1,32 +1,68 @@
-#![no_std]
+#![cfg_attr(not(test), no_std)]
 
 use asr::{
-    future::next_tick,
+    future::{next_tick, retry},
     game_engine::unity::il2cpp::Module,
     settings::{gui::Title as Heading, Gui},
     timer::{self, TimerState},
-    watcher::{Pair, Watcher},
+    watcher::Watcher,
     Process,
 };
 use core::{marker::PhantomData, ops::ControlFlow};
-use num_enum::IntoPrimitive;
-
-use crate::data::{BattleResult, Data, Item, Location, Monster};
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+
+use crate::{
+    data::{BattleResult, Data, Formation, Game, GameState, Item, Job, Location, Monster, Profile},
+    health::HealthCheck,
+    order::{Pending, Segment, SplitOrder},
+    route::{Route, RouteSplit, SeenRoute},
+    stats::Encounters,
+    trace::Recorder,
+    transitions::{MapSplits, Transition, Transitions, Visits},
+    variables::Variables,
+};
 
 mod data;
-
+mod health;
+mod order;
+mod route;
+#[cfg(test)]
+mod script;
+mod stats;
+#[cfg(test)]
+mod tests;
+mod trace;
+mod transitions;
+mod variables;
+
+#[cfg(not(test))]
 asr::async_main!(stable);
+#[cfg(not(test))]
 asr::panic_handler!();
 
+// The runtime entry point is only called by `async_main!`, which is not
+// compiled for tests. Without a reference, everything it reaches is dead.
+#[cfg(test)]
+const _: () = {
+    let _ = main;
+};
+
 #[macro_export]
 macro_rules! log {
     ($($arg:tt)*) => {{
-        let mut buf = ::asr::arrayvec::ArrayString::<8192>::new();
-        let _ = ::core::fmt::Write::write_fmt(
-            &mut buf,
-            ::core::format_args!($($arg)*),
-        );
-        ::asr::print_message(&buf);
+        #[cfg(not(test))]
+        {
+            let mut buf = ::asr::arrayvec::ArrayString::<8192>::new();
+            let _ = ::core::fmt::Write::write_fmt(
+                &mut buf,
+                ::core::format_args!($($arg)*),
+            );
+            ::asr::print_message(&buf);
+        }
+        #[cfg(test)]
+        {
+            ::std::println!($($arg)*);
+        }
     }};
 }
 
@@ -62,6 +98,33 @@ pub enum BattleSplit {
     BattleEnd,
 }
 
+#[derive(Gui, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Occurrence {
+    /// The 1st time
+    #[default]
+    First,
+
+    /// The 2nd time
+    Second,
+
+    /// The 3rd time
+    Third,
+
+    /// The 4th time
+    Fourth,
+}
+
+impl Occurrence {
+    const fn count(self) -> u8 {
+        match self {
+            Occurrence::First => 1,
+            Occurrence::Second => 2,
+            Occurrence::Third => 3,
+            Occurrence::Fourth => 4,
+        }
+    }
+}
+
 #[derive(Gui)]
 pub struct Settings {
     /// General Settings
@@ -71,6 +134,14 @@ pub struct Settings {
     #[default = true]
     start: bool,
 
+    /// Reset the timer when starting a new game from the title screen
+    #[default = false]
+    reset: bool,
+
+    /// Practice: start the timer when loading a save, restart it on every reload
+    #[default = false]
+    practice: bool,
+
     /// Split when defeating Chaos
     #[default = true]
     chaos: bool,
@@ -78,6 +149,22 @@ pub struct Settings {
     /// When to split on battles.
     battle_split: BattleSplit,
 
+    /// Only split when the split matches the current segment in the split order
+    #[default = false]
+    ordered: bool,
+
+    /// Pause game time during loads and fades (load removed timing)
+    #[default = false]
+    load_removal: bool,
+
+    /// Marathon: keep the run going across the Pixel Remaster games and split when the next game starts
+    #[default = false]
+    marathon: bool,
+
+    /// Record a trace of the game state to the log (for bug reports)
+    #[default = false]
+    record: bool,
+
     /// Splits: Only enable the settings that match your splits!
     _splits_heading1: Heading,
 
@@ -107,10 +194,16 @@ pub struct Settings {
     #[default = false]
     elfen_shop: bool,
 
+    /// Which visit to the Elfenheim item shop splits
+    elfen_shop_count: Occurrence,
+
     /// Split when entering the Marsh Cave
     #[default = false]
     marsh_cave: bool,
 
+    /// Which time entering the Marsh Cave splits
+    marsh_cave_count: Occurrence,
+
     /// Split when defeating Piscodemons
     #[default = false]
     piscodemons: bool,
@@ -143,10 +236,16 @@ pub struct Settings {
     #[default = false]
     cornelia_shop: bool,
 
+    /// Which visit to the Cornelia item shop splits
+    cornelia_shop_count: Occurrence,
+
     /// Split when having bought Firaga
     #[default = false]
     firaga: bool,
 
+    /// Which visit to the Melmond black magic shop splits
+    firaga_count: Occurrence,
+
     /// Split when defeating Vampire
     #[default = false]
     vampire: bool,
@@ -167,6 +266,9 @@ pub struct Settings {
     #[default = false]
     crescent_lake_shop: bool,
 
+    /// Which visit to the Crescent Lake item shop splits
+    crescent_lake_shop_count: Occurrence,
+
     /// Split when obtaining the Canoe
     #[default = false]
     canoe: bool,
@@ -183,10 +285,21 @@ pub struct Settings {
     #[default = false]
     ice_cave: bool,
 
+    /// Which time leaving the Ice Cave splits
+    ice_cave_count: Occurrence,
+
     /// Split when obtaining the Air Ship
     #[default = false]
     air_ship: bool,
 
+    /// Split when obtaining the Rat's Tail
+    #[default = false]
+    rats_tail: bool,
+
+    /// Split when the whole party changed their class
+    #[default = false]
+    class_change: bool,
+
     /// Split when obtaining the Warp Cube
     #[default = false]
     warp_cube: bool,
@@ -195,6 +308,9 @@ pub struct Settings {
     #[default = false]
     waterfall_cave: bool,
 
+    /// Which time leaving the Waterfall Cave splits
+    waterfall_cave_count: Occurrence,
+
     /// Split when obtaining the Bottled Faerie
     #[default = false]
     bottled_faerie: bool,
@@ -203,6 +319,9 @@ pub struct Settings {
     #[default = false]
     gaia_shop: bool,
 
+    /// Which visit to the Gaia item shop splits
+    gaia_shop_count: Occurrence,
+
     /// Split when obtaining the Oxyale
     #[default = false]
     oxyale: bool,
@@ -211,6 +330,9 @@ pub struct Settings {
     #[default = false]
     onrac_shop: bool,
 
+    /// Which visit to the Onrac item shop splits
+    onrac_shop_count: Occurrence,
+
     /// Split when obtaining the Rosetta Stone
     #[default = false]
     rosetta_stone: bool,
@@ -231,6 +353,17 @@ pub struct Settings {
     #[default = false]
     flying_fortress: bool,
 
+    /// Which time entering the Flying Fortress splits
+    flying_fortress_count: Occurrence,
+
+    /// Split when obtaining the Adamantite
+    #[default = false]
+    adamantite: bool,
+
+    /// Split when trading the Adamantite to the smith
+    #[default = false]
+    adamantite_trade: bool,
+
     /// Split when defeating Tiamat
     #[default = false]
     tiamat: bool,
@@ -247,6 +380,9 @@ pub struct Settings {
     #[default = false]
     chaos_shrine: bool,
 
+    /// Which time going down the Chaos Shrine stairs splits
+    chaos_shrine_count: Occurrence,
+
     /// Split when defeating Lich 2
     #[default = false]
     lich2: bool,
@@ -262,10 +398,76 @@ pub struct Settings {
     /// Split when defeating Tiamat 2
     #[default = false]
     tiamat2: bool,
+
+    /// Item splits: split when a key item leaves the inventory
+    _used_heading: Heading,
+
+    /// Split when the Crown is used or given away
+    #[default = false]
+    crown_used: bool,
+
+    /// Split when the Crystal Eye is used or given away
+    #[default = false]
+    crystal_eye_used: bool,
+
+    /// Split when the Tonic is used or given away
+    #[default = false]
+    tonic_used: bool,
+
+    /// Split when the Mystic Key is used or given away
+    #[default = false]
+    mystic_key_used: bool,
+
+    /// Split when the Nitro is used or given away
+    #[default = false]
+    nitro_used: bool,
+
+    /// Split when the Star Ruby is used or given away
+    #[default = false]
+    star_ruby_used: bool,
+
+    /// Split when the Earth Rod is used or given away
+    #[default = false]
+    earth_rod_used: bool,
+
+    /// Split when the Canoe is used or given away
+    #[default = false]
+    canoe_used: bool,
+
+    /// Split when the Levi Stone is used or given away
+    #[default = false]
+    levi_stone_used: bool,
+
+    /// Split when the Warp Cube is used or given away
+    #[default = false]
+    warp_cube_used: bool,
+
+    /// Split when the Bottled Faerie is used or given away
+    #[default = false]
+    bottled_faerie_used: bool,
+
+    /// Split when the Oxyale is used or given away
+    #[default = false]
+    oxyale_used: bool,
+
+    /// Split when the Rosetta Stone is used or given away
+    #[default = false]
+    rosetta_stone_used: bool,
+
+    /// Split when the Chime is used or given away
+    #[default = false]
+    chime_used: bool,
+
+    /// Split when the Rat's Tail is used or given away
+    #[default = false]
+    rats_tail_used: bool,
 }
 
+/// How many times per second the game state is checked.
+const TICK_RATE: u32 = 60;
+
 async fn main() {
-    asr::set_tick_rate(60.0);
+    asr::set_tick_rate(f64::from(TICK_RATE));
 
     let mut settings = {
         let mut s = Settings::register();
@@ -273,13 +475,38 @@ async fn main() {
         log!("Loaded settings: {:?}", SettingsDebug(&s));
         s
     };
+    MapSplits::register();
+
+    let mut state = State::NotRunning(Title::new());
+    let mut last_game = None;
 
     loop {
-        let process = Process::wait_attach("FINAL FANTASY.exe").await;
-        log!("attached to process");
+        // The later games are only supported as part of a marathon
+        let marathon = settings.marathon;
+        let (game, process) = retry(|| {
+            Game::ALL
+                .into_iter()
+                .filter(|game| marathon || game.is_marathon_only() == false)
+                .find_map(|game| Some((game, Process::attach(game.process_name())?)))
+        })
+        .await;
+        log!("attached to process: {game:?}");
+
+        if settings.marathon
+            && last_game.is_some_and(|last| last != game)
+            && matches!(timer::state(), TimerState::Running)
+        {
+            log!("Splitting: next game {game:?}");
+            timer::split();
+        }
+        last_game = Some(game);
+
         process
-            .until_closes(game_loop(&process, &mut settings))
+            .until_closes(game_loop(&process, game, &mut settings, &mut state))
             .await;
+
+        // Game time might still be paused from a load when the game closed
+        timer::resume_game_time();
     }
 }
 
@@ -288,46 +515,214 @@ enum State {
     Running(Splits),
 }
 
-async fn game_loop(process: &Process, settings: &mut Settings) {
+/// How the splits are detected, as configured in the settings.
+struct Rules<'a> {
+    battle_split: BattleSplit,
+    route: &'a Route,
+    transitions: &'a MapSplits,
+    occurrences: Counters,
+}
+
+async fn game_loop(process: &Process, game: Game, settings: &mut Settings, state: &mut State) {
     let module = Module::wait_attach_auto_detect(process).await;
     let image = module.wait_get_default_image(process).await;
     log!("Attached to the game");
 
-    let data = Data::new(process, &module, &image).await;
+    // The size identifies the build in bug reports
+    let size = process
+        .get_module_size("GameAssembly.dll")
+        .unwrap_or_default();
+    let Some(profile) = Profile::detect(process, &module, &image).await else {
+        log!("Unsupported version of {game:?} (GameAssembly.dll size: {size})");
+        log!("None of the known builds match, the autosplitter will not do anything");
+        loop {
+            next_tick().await;
+        }
+    };
+    log!(
+        "Detected the {} build of {game:?} (GameAssembly.dll size: {size})",
+        profile.name
+    );
+
+    let mut data = Data::new(process, game, profile, &module, &image).await;
     log!("Loaded game data");
 
-    let mut state = State::NotRunning(Title::new());
+    match state {
+        // A marathon continues with the splits of the previous game
+        State::Running(splits) if settings.marathon => {
+            splits.next_game();
+            splits.seed(&data);
+        }
+        _ => *state = State::NotRunning(Title::new()),
+    }
+
+    let mut recorder = Recorder::new();
+    let mut loads = LoadRemoval::new();
+    let mut variables = Variables::new();
+    let mut health = HealthCheck::new();
+    let mut order = SplitOrder::empty();
+    let mut pending = Pending::new();
+    let mut route = Route::empty();
+    let mut transitions = MapSplits::empty();
+    let mut last_split = None;
+
+    // Loaded once for runs that are already in progress
+    if settings.ordered {
+        order.reload();
+    }
+    route.reload();
+    transitions.reload();
 
     'outer: loop {
         settings.update();
-        match main_loop(&data, &mut state, settings.battle_split) {
+        data.update();
+
+        if let State::NotRunning(_) = *state {
+            last_split = None;
+            pending.clear();
+
+            // The order, the route, and the map splits can only change between runs
+            if settings.ordered {
+                order.reload();
+            }
+            route.reload();
+            transitions.reload();
+        }
+
+        let rules = Rules {
+            battle_split: settings.battle_split,
+            route: &route,
+            transitions: &transitions,
+            occurrences: settings.occurrences(),
+        };
+
+        match main_loop(&data, state, timer::state(), &rules) {
             ControlFlow::Continue(()) => continue 'outer,
             ControlFlow::Break(Action::Start) if settings.start => {
                 log!("Starting timer");
                 timer::start();
             }
-            ControlFlow::Break(Action::Split(split)) if settings.filter(split) => {
+            ControlFlow::Break(Action::Reset) if settings.reset => {
+                log!("Resetting timer");
+                timer::reset();
+                if settings.start {
+                    log!("Starting timer");
+                    timer::start();
+                }
+                *state = State::Running(Splits::new());
+                last_split = None;
+                pending.clear();
+            }
+            ControlFlow::Break(Action::Split(split))
+                if settings.filter(split)
+                    && (settings.ordered == false || order.allows(Segment::Split(split))) =>
+            {
                 log!("Splitting: {split:?}");
                 timer::split();
+                last_split = Some(split);
+            }
+            ControlFlow::Break(Action::Split(split)) if settings.filter(split) => {
+                log!("Out of order: {split:?}, waiting for its segment");
+                pending.push(Segment::Split(split));
             }
             ControlFlow::Break(Action::Start) => {
                 log!("Ignoring: Start");
             }
+            ControlFlow::Break(Action::Reset) => {
+                log!("Ignoring: Reset");
+            }
+            ControlFlow::Break(Action::Load) if settings.practice => {
+                log!("Restarting timer for practice");
+                timer::reset();
+                timer::start();
+                *state = State::Running(Splits::resume(&data));
+                last_split = None;
+                pending.clear();
+            }
+            ControlFlow::Break(Action::Load) => {}
             ControlFlow::Break(Action::Split(split)) => {
                 log!("Ignoring: {split:?}");
             }
+            ControlFlow::Break(Action::Undo(split)) if last_split == Some(split) => {
+                log!("Undoing: {split:?}");
+                timer::undo_split();
+                last_split = None;
+            }
+            ControlFlow::Break(Action::Undo(split)) => {
+                log!("Ignoring undo: {split:?}");
+                pending.remove(Segment::Split(split));
+            }
+            ControlFlow::Break(Action::Transition(transition))
+                if settings.ordered == false || order.allows(Segment::Transition(transition)) =>
+            {
+                log!("Splitting: {transition:?}");
+                timer::split();
+                last_split = None;
+            }
+            ControlFlow::Break(Action::Transition(transition)) => {
+                log!("Out of order: {transition:?}, waiting for its segment");
+                pending.push(Segment::Transition(transition));
+            }
+            ControlFlow::Break(Action::Route(split))
+                if settings.ordered == false || order.allows(Segment::Route(split)) =>
+            {
+                log!("Splitting: {split:?}");
+                timer::split();
+                last_split = None;
+            }
+            ControlFlow::Break(Action::Route(split)) => {
+                log!("Out of order: {split:?}, waiting for its segment");
+                pending.push(Segment::Route(split));
+            }
+            ControlFlow::Break(Action::Used(item))
+                if settings.used(item)
+                    && (settings.ordered == false || order.allows(Segment::Used(item))) =>
+            {
+                log!("Splitting: {item:?} used");
+                timer::split();
+                last_split = None;
+            }
+            ControlFlow::Break(Action::Used(item)) if settings.used(item) => {
+                log!("Out of order: {item:?} used, waiting for its segment");
+                pending.push(Segment::Used(item));
+            }
+            ControlFlow::Break(Action::Used(item)) => {
+                log!("Ignoring: {item:?} used");
+            }
             ControlFlow::Break(Action::None) => {}
         }
 
+        // A split that happened early splits once the timer reaches its segment
+        if settings.ordered {
+            if let Some(split) = pending.take_current(&order) {
+                log!("Splitting: {split:?} (waited for its segment)");
+                timer::split();
+                last_split = None;
+            }
+        }
+
+        loads.update(&data, settings.load_removal);
+        variables.update(&data, state);
+        health.update(&data);
+
+        if settings.record {
+            recorder.record(&data);
+        }
+
         next_tick().await;
     }
 }
 
-fn main_loop(data: &Data<'_>, state: &mut State, battle_split: BattleSplit) -> ControlFlow<Action> {
+fn main_loop<G: GameState>(
+    data: &G,
+    state: &mut State,
+    timer_state: TimerState,
+    rules: &Rules<'_>,
+) -> ControlFlow<Action> {
     match state {
-        State::NotRunning(title) => match timer::state() {
+        State::NotRunning(title) => match timer_state {
             TimerState::Running => {
-                *state = State::Running(Splits::new());
+                *state = State::Running(Splits::resume(data));
                 return ControlFlow::Continue(());
             }
             TimerState::NotRunning => {
@@ -335,20 +730,27 @@ fn main_loop(data: &Data<'_>, state: &mut State, battle_split: BattleSplit) -> C
                     *state = State::Running(Splits::new());
                     return ControlFlow::Break(Action::Start);
                 }
+                if title.save_loaded(data) {
+                    return ControlFlow::Break(Action::Load);
+                }
             }
             _ => {}
         },
-        State::Running(splits) => match timer::state() {
+        State::Running(splits) => match timer_state {
             TimerState::NotRunning => {
                 *state = State::NotRunning(Title::new());
                 return ControlFlow::Continue(());
             }
-            TimerState::Running => {
-                if let Some(split) = splits.check(data, battle_split) {
-                    return ControlFlow::Break(Action::Split(split));
+            timer_state => {
+                if let Some(action) = splits.title_check(data) {
+                    return ControlFlow::Break(action);
+                }
+                if matches!(timer_state, TimerState::Running) {
+                    if let Some(action) = splits.check(data, rules) {
+                        return ControlFlow::Break(action);
+                    }
                 }
             }
-            _ => {}
         },
     };
     ControlFlow::Break(Action::None)
@@ -358,10 +760,32 @@ fn main_loop(data: &Data<'_>, state: &mut State, battle_split: BattleSplit) -> C
 enum Action {
     None,
     Start,
+    Reset,
+    Load,
     Split(SplitOn),
+    Undo(SplitOn),
+    Route(RouteSplit),
+    Transition(Transition),
+    Used(Item),
+}
+
+/// Something that happened in the game that can be split on.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Event {
+    BattleWon {
+        monster: Monster,
+        formation: Option<Formation>,
+    },
+    Moved {
+        from: Location,
+        to: Location,
+    },
+    Obtained(Item),
+    Used(Item),
+    ClassChange,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, IntoPrimitive)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, IntoPrimitive, TryFromPrimitive)]
 #[repr(u8)]
 enum SplitOn {
     Garland,
@@ -389,6 +813,8 @@ enum SplitOn {
     LeviStone,
     IceCave,
     AirShip,
+    RatsTail,
+    ClassChange,
     WarpCube,
     WaterfallCave,
     BottledFaerie,
@@ -400,6 +826,8 @@ enum SplitOn {
     Chime,
     BlueDragon,
     FlyingFortress,
+    Adamantite,
+    AdamantiteTrade,
     Tiamat,
     Marilith,
     DeathEye,
@@ -412,8 +840,128 @@ enum SplitOn {
 }
 
 impl SplitOn {
-    fn from_watcher(watcher: &Pair<Location>) -> Option<Self> {
-        match (watcher.old, watcher.current) {
+    /// The name of the setting for this split.
+    fn key(self) -> &'static str {
+        match self {
+            SplitOn::Garland => "garland",
+            SplitOn::Lute => "lute",
+            SplitOn::Pirates => "pirates",
+            SplitOn::Ship => "ship",
+            SplitOn::ElfenheimItemShop => "elfen_shop",
+            SplitOn::EnterMarshCave => "marsh_cave",
+            SplitOn::Piscodemons => "piscodemons",
+            SplitOn::Crown => "crown",
+            SplitOn::Astos => "astos",
+            SplitOn::CrystalEye => "crystal_eye",
+            SplitOn::Tonic => "tonic",
+            SplitOn::MysticKey => "mystic_key",
+            SplitOn::Nitro => "nitro",
+            SplitOn::CorneliaItemShop => "cornelia_shop",
+            SplitOn::Firaga => "firaga",
+            SplitOn::Vampire => "vampire",
+            SplitOn::StarRuby => "star_ruby",
+            SplitOn::EarthRod => "earth_rod",
+            SplitOn::Lich => "lich",
+            SplitOn::CrescentLakeShop => "crescent_lake_shop",
+            SplitOn::Canoe => "canoe",
+            SplitOn::EvilEye => "evil_eye",
+            SplitOn::LeviStone => "levi_stone",
+            SplitOn::IceCave => "ice_cave",
+            SplitOn::AirShip => "air_ship",
+            SplitOn::RatsTail => "rats_tail",
+            SplitOn::ClassChange => "class_change",
+            SplitOn::WarpCube => "warp_cube",
+            SplitOn::WaterfallCave => "waterfall_cave",
+            SplitOn::BottledFaerie => "bottled_faerie",
+            SplitOn::GaiaItemShop => "gaia_shop",
+            SplitOn::Oxyale => "oxyale",
+            SplitOn::OnracItemShop => "onrac_shop",
+            SplitOn::RosettaStone => "rosetta_stone",
+            SplitOn::Kraken => "kraken",
+            SplitOn::Chime => "chime",
+            SplitOn::BlueDragon => "blue_dragon",
+            SplitOn::FlyingFortress => "flying_fortress",
+            SplitOn::Adamantite => "adamantite",
+            SplitOn::AdamantiteTrade => "adamantite_trade",
+            SplitOn::Tiamat => "tiamat",
+            SplitOn::Marilith => "marilith",
+            SplitOn::DeathEye => "death_eye",
+            SplitOn::ChaosShrine => "chaos_shrine",
+            SplitOn::Lich2 => "lich2",
+            SplitOn::Marilith2 => "marilith2",
+            SplitOn::Kraken2 => "kraken2",
+            SplitOn::Tiamat2 => "tiamat2",
+            SplitOn::Chaos => "chaos",
+        }
+    }
+
+    fn from_key(key: &str) -> Option<Self> {
+        (0..=u8::MAX)
+            .filter_map(|ord| Self::try_from_primitive(ord).ok())
+            .find(|split| split.key() == key)
+    }
+
+    fn from_monster(monster: Monster) -> Option<Self> {
+        match monster {
+            Monster::Garland => Some(SplitOn::Garland),
+            Monster::Pirates => Some(SplitOn::Pirates),
+            Monster::Piscodemons => Some(SplitOn::Piscodemons),
+            Monster::Astos => Some(SplitOn::Astos),
+            Monster::Vampire => Some(SplitOn::Vampire),
+            Monster::Lich => Some(SplitOn::Lich),
+            Monster::EvilEye => Some(SplitOn::EvilEye),
+            Monster::Kraken => Some(SplitOn::Kraken),
+            Monster::BlueDragon => Some(SplitOn::BlueDragon),
+            Monster::Tiamat => Some(SplitOn::Tiamat),
+            Monster::Marilith => Some(SplitOn::Marilith),
+            Monster::DeathEye => Some(SplitOn::DeathEye),
+            Monster::Lich2 => Some(SplitOn::Lich2),
+            Monster::Marilith2 => Some(SplitOn::Marilith2),
+            Monster::Kraken2 => Some(SplitOn::Kraken2),
+            Monster::Tiamat2 => Some(SplitOn::Tiamat2),
+            Monster::Chaos => Some(SplitOn::Chaos),
+            Monster::Unknown(_) => None,
+        }
+    }
+
+    fn from_item(item: Item) -> Self {
+        match item {
+            Item::Lute => SplitOn::Lute,
+            Item::Ship => SplitOn::Ship,
+            Item::Crown => SplitOn::Crown,
+            Item::CrystalEye => SplitOn::CrystalEye,
+            Item::Tonic => SplitOn::Tonic,
+            Item::MysticKey => SplitOn::MysticKey,
+            Item::Nitro => SplitOn::Nitro,
+            Item::StarRuby => SplitOn::StarRuby,
+            Item::EarthRod => SplitOn::EarthRod,
+            Item::Canoe => SplitOn::Canoe,
+            Item::LeviStone => SplitOn::LeviStone,
+            Item::AirShip => SplitOn::AirShip,
+            Item::RatsTail => SplitOn::RatsTail,
+            Item::Adamantite => SplitOn::Adamantite,
+            Item::WarpCube => SplitOn::WarpCube,
+            Item::BottledFaerie => SplitOn::BottledFaerie,
+            Item::Oxyale => SplitOn::Oxyale,
+            Item::RosettaStone => SplitOn::RosettaStone,
+            Item::Chime => SplitOn::Chime,
+        }
+    }
+
+    fn from_event(event: Event) -> Option<Self> {
+        match event {
+            Event::BattleWon { monster, .. } => Self::from_monster(monster),
+            Event::Moved { from, to } => Self::from_move(from, to),
+            Event::Obtained(item) => Some(Self::from_item(item)),
+            // The smith takes the Adamantite in exchange for Excalibur
+            Event::Used(Item::Adamantite) => Some(Self::AdamantiteTrade),
+            Event::Used(_) => None,
+            Event::ClassChange => Some(Self::ClassChange),
+        }
+    }
+
+    fn from_move(from: Location, to: Location) -> Option<Self> {
+        match (from, to) {
             (Location::ElfenheimItemShop, Location::Elfenheim) => Some(Self::ElfenheimItemShop),
             (Location::WorldMap, Location::MarshCave1) => Some(Self::EnterMarshCave),
             (Location::CorneliaItemShop, Location::Cornelia) => Some(Self::CorneliaItemShop),
@@ -435,7 +983,13 @@ impl Settings {
         let Settings {
             _general,
             start: _,
+            reset: _,
+            practice: _,
             battle_split: _,
+            ordered: _,
+            load_removal: _,
+            marathon: _,
+            record: _,
             _splits_heading1,
             _splits_heading2,
             _splits_heading3,
@@ -444,7 +998,9 @@ impl Settings {
             pirates,
             ship,
             elfen_shop,
+            elfen_shop_count: _,
             marsh_cave,
+            marsh_cave_count: _,
             piscodemons,
             crown,
             astos,
@@ -453,37 +1009,66 @@ impl Settings {
             mystic_key,
             nitro,
             cornelia_shop,
+            cornelia_shop_count: _,
             firaga,
+            firaga_count: _,
             vampire,
             star_ruby,
             earth_rod,
             lich,
             crescent_lake_shop,
+            crescent_lake_shop_count: _,
             canoe,
             evil_eye,
             levi_stone,
             ice_cave,
+            ice_cave_count: _,
             air_ship,
+            rats_tail,
+            class_change,
             warp_cube,
             waterfall_cave,
+            waterfall_cave_count: _,
             bottled_faerie,
             gaia_shop,
+            gaia_shop_count: _,
             oxyale,
             onrac_shop,
+            onrac_shop_count: _,
             rosetta_stone,
             kraken,
             chime,
             blue_dragon,
             flying_fortress,
+            flying_fortress_count: _,
+            adamantite,
+            adamantite_trade,
             tiamat,
             marilith,
             death_eye,
             chaos_shrine,
+            chaos_shrine_count: _,
             lich2,
             marilith2,
             kraken2,
             tiamat2,
             chaos,
+            _used_heading,
+            crown_used: _,
+            crystal_eye_used: _,
+            tonic_used: _,
+            mystic_key_used: _,
+            nitro_used: _,
+            star_ruby_used: _,
+            earth_rod_used: _,
+            canoe_used: _,
+            levi_stone_used: _,
+            warp_cube_used: _,
+            bottled_faerie_used: _,
+            oxyale_used: _,
+            rosetta_stone_used: _,
+            chime_used: _,
+            rats_tail_used: _,
         } = self;
         return match split {
             SplitOn::Garland => *garland,
@@ -511,6 +1096,8 @@ impl Settings {
             SplitOn::LeviStone => *levi_stone,
             SplitOn::IceCave => *ice_cave,
             SplitOn::AirShip => *air_ship,
+            SplitOn::RatsTail => *rats_tail,
+            SplitOn::ClassChange => *class_change,
             SplitOn::WarpCube => *warp_cube,
             SplitOn::WaterfallCave => *waterfall_cave,
             SplitOn::BottledFaerie => *bottled_faerie,
@@ -522,6 +1109,8 @@ impl Settings {
             SplitOn::Chime => *chime,
             SplitOn::BlueDragon => *blue_dragon,
             SplitOn::FlyingFortress => *flying_fortress,
+            SplitOn::Adamantite => *adamantite,
+            SplitOn::AdamantiteTrade => *adamantite_trade,
             SplitOn::Tiamat => *tiamat,
             SplitOn::Marilith => *marilith,
             SplitOn::DeathEye => *death_eye,
@@ -535,98 +1124,327 @@ impl Settings {
     }
 }
 
+impl Settings {
+    /// Whether to split when the item is used or given away.
+    fn used(&self, item: Item) -> bool {
+        return match item {
+            // The Lute stays in the inventory after opening the Chaos Shrine
+            Item::Lute => false,
+            Item::Crown => self.crown_used,
+            Item::CrystalEye => self.crystal_eye_used,
+            Item::Tonic => self.tonic_used,
+            Item::MysticKey => self.mystic_key_used,
+            Item::Nitro => self.nitro_used,
+            Item::StarRuby => self.star_ruby_used,
+            Item::EarthRod => self.earth_rod_used,
+            Item::Canoe => self.canoe_used,
+            Item::LeviStone => self.levi_stone_used,
+            Item::WarpCube => self.warp_cube_used,
+            Item::BottledFaerie => self.bottled_faerie_used,
+            Item::Oxyale => self.oxyale_used,
+            Item::RosettaStone => self.rosetta_stone_used,
+            Item::Chime => self.chime_used,
+            Item::RatsTail => self.rats_tail_used,
+            // Handing over the Adamantite is the trade split
+            Item::Adamantite => false,
+            // Vehicles are never given away
+            Item::Ship | Item::AirShip => false,
+        };
+    }
+
+    /// Which occurrence of each split should split.
+    fn occurrences(&self) -> Counters {
+        let mut occurrences = Counters::filled(Occurrence::First.count());
+        for (split, occurrence) in [
+            (SplitOn::ElfenheimItemShop, self.elfen_shop_count),
+            (SplitOn::EnterMarshCave, self.marsh_cave_count),
+            (SplitOn::CorneliaItemShop, self.cornelia_shop_count),
+            (SplitOn::Firaga, self.firaga_count),
+            (SplitOn::CrescentLakeShop, self.crescent_lake_shop_count),
+            (SplitOn::IceCave, self.ice_cave_count),
+            (SplitOn::WaterfallCave, self.waterfall_cave_count),
+            (SplitOn::GaiaItemShop, self.gaia_shop_count),
+            (SplitOn::OnracItemShop, self.onrac_shop_count),
+            (SplitOn::FlyingFortress, self.flying_fortress_count),
+            (SplitOn::ChaosShrine, self.chaos_shrine_count),
+        ] {
+            occurrences.set(split, occurrence.count());
+        }
+        return occurrences;
+    }
+}
+
 #[derive(Copy, Clone, Debug)]
 struct NoBattle;
 
 impl Location {
     fn has_key_item(self) -> bool {
-        matches!(
-            self,
+        match self {
             Location::CorneliaThrone
-                | Location::Pravoka
-                | Location::MarshCave3
-                | Location::WesternKeep
-                | Location::MatoyaCave
-                | Location::ElvenCastle
-                | Location::CastleCornelia
-                | Location::EarthCave3
-                | Location::SageCave
-                | Location::CrescentLake
-                | Location::IceCave2
-                | Location::AirHangar
-                | Location::WaterfallCave
-                | Location::OasisShop
-                | Location::Gaia
-                | Location::Underwater5
-                | Location::Lufenia
-        )
+            | Location::Pravoka
+            | Location::MarshCave3
+            | Location::WesternKeep
+            | Location::MatoyaCave
+            | Location::ElvenCastle
+            | Location::CastleCornelia
+            | Location::EarthCave3
+            | Location::SageCave
+            | Location::CrescentLake
+            | Location::IceCave2
+            | Location::AirHangar
+            | Location::WaterfallCave
+            | Location::OasisShop
+            | Location::Gaia
+            | Location::Underwater5
+            | Location::Lufenia => true,
+            // The Adamantite is on one of the upper floors of the Flying Fortress
+            Location::Unknown(_)
+                if self
+                    .area()
+                    .is_some_and(|area| area.first == Location::FlyingFortress.id()) =>
+            {
+                true
+            }
+            _ => false,
+        }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-struct EnumSet<T>(u64, PhantomData<T>);
+/// A set of game ids or indices, as a fixed size bitset of 64 bit words.
+///
+/// A single word covers the splits and the route lines, larger sets
+/// choose the number of words that covers their ids.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct EnumSet<T, const WORDS: usize = 1>([u64; WORDS], PhantomData<T>);
+
+trait EnumSetMember: Sized {
+    fn ordinal(&self) -> Option<u16>;
 
-trait EnumSetMember {
-    fn ordinal(&self) -> Option<u8>;
+    fn from_ordinal(ordinal: u16) -> Option<Self>;
 }
 
-impl<T: EnumSetMember> EnumSet<T> {
+impl<T: EnumSetMember, const WORDS: usize> EnumSet<T, WORDS> {
+    /// The first ordinal that does not fit into the set.
+    const CAPACITY: u16 = WORDS as u16 * 64;
+
     const fn empty() -> Self {
-        Self(0, PhantomData)
+        Self([0; WORDS], PhantomData)
+    }
+
+    /// The word and the bit of the member, if it fits into the set.
+    fn position(item: &T) -> Option<(usize, u64)> {
+        let ord = item.ordinal()?;
+        if ord >= Self::CAPACITY {
+            return None;
+        }
+
+        return Some((usize::from(ord / 64), 1_u64 << (ord % 64)));
     }
 
     fn insert(&mut self, item: &T) -> bool {
-        let Some(ord) = item.ordinal() else {
+        let Some((word, mask)) = Self::position(item) else {
             return false;
         };
-        if ord >= 64 {
-            return false;
-        }
 
-        let mask = 1_u64 << ord;
-        let previous = self.0 & mask;
-        self.0 |= mask;
+        let previous = self.0[word] & mask;
+        self.0[word] |= mask;
         return previous == 0;
     }
+
+    fn remove(&mut self, item: &T) -> bool {
+        let Some((word, mask)) = Self::position(item) else {
+            return false;
+        };
+
+        let previous = self.0[word] & mask;
+        self.0[word] &= !mask;
+        return previous != 0;
+    }
+
+    fn contains(&self, item: &T) -> bool {
+        let Some((word, mask)) = Self::position(item) else {
+            return false;
+        };
+
+        return self.0[word] & mask != 0;
+    }
+
+    fn len(&self) -> u32 {
+        return self.0.iter().map(|word| word.count_ones()).sum();
+    }
+
+    /// All members in the order of their ordinals, only visiting the set bits.
+    fn iter(&self) -> impl Iterator<Item = T> + '_ {
+        self.0
+            .iter()
+            .enumerate()
+            .flat_map(|(index, &word)| {
+                let mut bits = word;
+                core::iter::from_fn(move || {
+                    if bits == 0 {
+                        return None;
+                    }
+                    let bit = bits.trailing_zeros();
+                    bits &= bits - 1;
+                    return Some(index as u16 * 64 + bit as u16);
+                })
+            })
+            .filter_map(T::from_ordinal)
+    }
 }
 
 impl EnumSetMember for SplitOn {
-    fn ordinal(&self) -> Option<u8> {
-        Some(u8::from(*self))
+    fn ordinal(&self) -> Option<u16> {
+        Some(u16::from(u8::from(*self)))
+    }
+
+    fn from_ordinal(ordinal: u16) -> Option<Self> {
+        Self::try_from_primitive(u8::try_from(ordinal).ok()?).ok()
+    }
+}
+
+/// A small counter for each split.
+#[derive(Debug, Clone, Copy)]
+struct Counters([u8; 64]);
+
+impl Counters {
+    const fn new() -> Self {
+        Self::filled(0)
+    }
+
+    const fn filled(count: u8) -> Self {
+        Self([count; 64])
+    }
+
+    fn get(&self, split: SplitOn) -> u8 {
+        return self
+            .0
+            .get(usize::from(u8::from(split)))
+            .copied()
+            .unwrap_or(0);
+    }
+
+    fn set(&mut self, split: SplitOn, count: u8) {
+        if let Some(counter) = self.0.get_mut(usize::from(u8::from(split))) {
+            *counter = count;
+        }
+    }
+
+    /// Counts the split once more and returns the new count.
+    fn increment(&mut self, split: SplitOn) -> u8 {
+        let count = self.get(split).saturating_add(1);
+        self.set(split, count);
+        return count;
     }
 }
 
-type Inventory = EnumSet<Item>;
+/// The words of an [`Inventory`], which covers the whole id space of the
+/// items, so that weapons, armor and consumables can be tracked next to
+/// the key items.
+const INVENTORY_WORDS: usize = 16;
+
+type Inventory = EnumSet<Item, INVENTORY_WORDS>;
 type SeenSplits = EnumSet<SplitOn>;
 
 struct Title {
     fade_out: Watcher<bool>,
+    in_title: Watcher<bool>,
+    started: bool,
+    /// The title of the next game in a marathon, which continues the run.
+    next_game: bool,
 }
 
 impl Title {
     fn new() -> Self {
         Self {
             fade_out: Watcher::new(),
+            in_title: Watcher::new(),
+            started: false,
+            next_game: false,
+        }
+    }
+
+    /// Starting the next game of a marathon neither resets nor loads.
+    fn next_game() -> Self {
+        Self {
+            started: true,
+            next_game: true,
+            ..Self::new()
         }
     }
 
-    fn new_game(&mut self, data: &Data) -> bool {
+    fn new_game<G: GameState>(&mut self, data: &G) -> bool {
         let fade_out = self.fade_out.update_infallible(data.has_fade_out());
         if fade_out.changed_to(&true) {
             log!("Fade out detected");
+            self.started = true;
+            return true;
+        }
+        return false;
+    }
+
+    /// Whether the title screen was left without starting a new game.
+    fn save_loaded<G: GameState>(&mut self, data: &G) -> bool {
+        let in_title = self.in_title.update_infallible(data.in_title());
+        if in_title.changed_to(&true) {
+            self.started = false;
+        }
+        if in_title.changed_to(&false) && self.started == false {
+            log!("Loaded a save");
             return true;
         }
         return false;
     }
 }
 
+struct LoadRemoval {
+    paused: bool,
+}
+
+impl LoadRemoval {
+    fn new() -> Self {
+        Self { paused: false }
+    }
+
+    fn update<G: GameState>(&mut self, data: &G, enabled: bool) {
+        // Game time is only paused while the timer is running, so that
+        // it is always resumed again when the timer stops or the setting
+        // gets disabled.
+        let paused = enabled && matches!(timer::state(), TimerState::Running) && data.is_loading();
+        if paused == self.paused {
+            return;
+        }
+
+        self.paused = paused;
+        if paused {
+            timer::pause_game_time();
+        } else {
+            timer::resume_game_time();
+        }
+    }
+}
+
 struct Splits {
     in_battle: Watcher<bool>,
     battle_result: Watcher<BattleResult>,
     location: Watcher<Location>,
+    promoted: Watcher<bool>,
     items: Inventory,
+    owned: Inventory,
+    missing: Option<Item>,
+    rats_tail_check: bool,
+    used: Inventory,
     seen: SeenSplits,
+    route: SeenRoute,
+    visits: Counters,
+    map_visits: Visits,
+    transitions: Transitions,
+    encounters: Encounters,
     chaos_end: f32,
+    tentative: Option<SplitOn>,
+    undo: Option<SplitOn>,
+    in_title: Watcher<bool>,
+    title: Option<Title>,
 }
 
 impl Splits {
@@ -635,78 +1453,282 @@ impl Splits {
             in_battle: Watcher::new(),
             battle_result: Watcher::new(),
             location: Watcher::new(),
+            promoted: Watcher::new(),
             items: Inventory::empty(),
+            owned: Inventory::empty(),
+            missing: None,
+            rats_tail_check: false,
+            used: Inventory::empty(),
             seen: SeenSplits::empty(),
+            route: SeenRoute::empty(),
+            visits: Counters::new(),
+            map_visits: Visits::new(),
+            transitions: Transitions::empty(),
+            encounters: Encounters::new(),
             chaos_end: f32::MAX,
+            tentative: None,
+            undo: None,
+            in_title: Watcher::new(),
+            title: None,
         }
     }
 
-    fn check(&mut self, data: &Data, split: BattleSplit) -> Option<SplitOn> {
-        let split = self.split_check(data, split)?;
-        self.seen.insert(&split).then_some(split)
+    /// Creates the splits for a run that is already in progress,
+    /// e.g. when the autosplitter attaches or a save is loaded.
+    ///
+    /// When this happens on the title screen, the items are only
+    /// seeded once the save is loaded.
+    fn resume<G: GameState>(data: &G) -> Self {
+        let mut splits = Self::new();
+        if data.in_title() {
+            splits.title = Some(Title::new());
+        }
+        splits.seed(data);
+        splits
     }
 
-    fn split_check(&mut self, data: &Data, split: BattleSplit) -> Option<SplitOn> {
+    /// Marks everything the party already owns as seen, so that
+    /// it does not split when the inventory is checked the next time.
+    ///
+    /// The key items that are owned now are the ones that can be given
+    /// away, an older save might not have the ones of the last save.
+    fn seed<G: GameState>(&mut self, data: &G) {
+        self.owned = Inventory::empty();
+        self.missing = None;
+        for item in data.key_item_ids() {
+            self.owned.insert(&item);
+        }
+
+        let mut seeded = 0;
+        for item in data.key_item_ids().chain(data.vehicle_ids()) {
+            if self.items.insert(&item) {
+                self.seen.insert(&SplitOn::from_item(item));
+                seeded += 1;
+            }
+        }
+        if seeded > 0 {
+            log!("Seeded {seeded} items from the save");
+        }
+    }
+
+    /// Starts over for the next game of a marathon, only keeping
+    /// what has already been split during the run.
+    fn next_game(&mut self) {
+        *self = Self {
+            seen: self.seen,
+            used: self.used,
+            route: self.route,
+            visits: self.visits,
+            map_visits: self.map_visits,
+            transitions: self.transitions,
+            encounters: self.encounters.clone(),
+            title: Some(Title::next_game()),
+            ..Self::new()
+        };
+    }
+
+    fn title_check<G: GameState>(&mut self, data: &G) -> Option<Action> {
+        let in_title = self.in_title.update_infallible(data.in_title());
+        let returned_to_title = in_title.changed_to(&true);
+        let left_title = in_title.changed_to(&false);
+
+        // The next game of a marathon boots into its title screen
+        if returned_to_title
+            && self
+                .title
+                .as_ref()
+                .is_none_or(|title| title.next_game == false)
+        {
+            log!("Returned to the title screen");
+            self.title = Some(Title::new());
+        }
+
+        let title = self.title.as_mut()?;
+
+        // Only a party confirmation after returning to the title screen
+        // is a new game. Fades during the game and loading a save don't
+        // go through the party confirmation, so they never reset.
+        if title.new_game(data) {
+            if title.next_game {
+                log!("Started the next game of the marathon");
+                return None;
+            }
+            return Some(Action::Reset);
+        }
+
+        if left_title {
+            let started = title.started;
+            self.title = None;
+
+            // A save was loaded, or a new game started with nothing to seed
+            self.seed(data);
+
+            if started == false {
+                log!("Loaded a save");
+                return Some(Action::Load);
+            }
+        }
+
+        return None;
+    }
+
+    fn check<G: GameState>(&mut self, data: &G, rules: &Rules<'_>) -> Option<Action> {
+        let split = rules.battle_split;
+        let event = self.event_check(data, split);
+
+        if let Some(undo) = self.undo.take() {
+            self.seen.remove(&undo);
+            return Some(Action::Undo(undo));
+        }
+
+        let event = event?;
+        let mut split_on = SplitOn::from_event(event);
+
+        // The map ids of other games are not the maps of Final Fantasy
+        let mapped = data.game().is_mapped();
+
+        if let (Event::Moved { from, to }, true) = (event, mapped) {
+            self.map_visits.count(from, to);
+        }
+
+        // Maps can be visited more than once, only the selected visit splits
+        if let (Event::Moved { .. }, Some(split)) = (event, split_on) {
+            let visit = self.visits.increment(split);
+            let occurrence = rules.occurrences.get(split);
+            if visit != occurrence {
+                log!("{split:?}: visit {visit}, splitting on visit {occurrence}");
+                split_on = None;
+            }
+        }
+
+        // The battle is not over yet and could still be lost
+        if let (Event::BattleWon { monster, .. }, Some(split_on)) = (event, split_on) {
+            if split == BattleSplit::DeathAnimation && monster != Monster::Chaos {
+                self.tentative = Some(split_on);
+            }
+        }
+
+        // The route takes precedence, the same event should not split twice
+        if let Some(line) = rules.route.find(data.game(), event, &self.route) {
+            self.route.insert(&line);
+            if let Some(split_on) = split_on {
+                self.seen.insert(&split_on);
+            }
+            self.tentative = None;
+            return Some(Action::Route(line));
+        }
+
+        if let (Event::Used(item), None) = (event, split_on) {
+            if self.used.insert(&item) {
+                return Some(Action::Used(item));
+            }
+            return None;
+        }
+
+        if let (Event::Moved { from, to }, true) = (event, mapped) {
+            if let Some(transition) =
+                rules
+                    .transitions
+                    .find(from, to, &self.transitions, &self.map_visits)
+            {
+                self.transitions.insert(transition);
+                if let Some(split_on) = split_on {
+                    self.seen.insert(&split_on);
+                }
+                return Some(Action::Transition(transition));
+            }
+        }
+
+        let split = split_on?;
+        if self.seen.insert(&split) {
+            return Some(Action::Split(split));
+        }
+
+        // Nothing to take back if this was not split in the first place
+        if self.tentative == Some(split) {
+            self.tentative = None;
+        }
+        return None;
+    }
+
+    fn event_check<G: GameState>(&mut self, data: &G, split: BattleSplit) -> Option<Event> {
         match self.battle_check(data, split)? {
             Ok(monster) => {
-                return Some(match monster {
-                    Monster::Garland => SplitOn::Garland,
-                    Monster::Pirates => SplitOn::Pirates,
-                    Monster::Piscodemons => SplitOn::Piscodemons,
-                    Monster::Astos => SplitOn::Astos,
-                    Monster::Vampire => SplitOn::Vampire,
-                    Monster::Lich => SplitOn::Lich,
-                    Monster::EvilEye => SplitOn::EvilEye,
-                    Monster::Kraken => SplitOn::Kraken,
-                    Monster::BlueDragon => SplitOn::BlueDragon,
-                    Monster::Tiamat => SplitOn::Tiamat,
-                    Monster::Marilith => SplitOn::Marilith,
-                    Monster::DeathEye => SplitOn::DeathEye,
-                    Monster::Lich2 => SplitOn::Lich2,
-                    Monster::Marilith2 => SplitOn::Marilith2,
-                    Monster::Kraken2 => SplitOn::Kraken2,
-                    Monster::Tiamat2 => SplitOn::Tiamat2,
-                    Monster::Chaos => SplitOn::Chaos,
-                })
+                let formation = data.formation();
+                return Some(Event::BattleWon { monster, formation });
             }
             Err(_no_battle) => {}
         }
 
-        let field = match self.field_check(data)? {
-            Ok(split) => return Some(split),
-            Err(field) => field,
-        };
+        if self.class_check(data) {
+            return Some(Event::ClassChange);
+        }
+
+        if let Some(item) = self.removal_check(data) {
+            return Some(Event::Used(item));
+        }
 
-        if field.has_key_item() {
+        // The map ids of other games are not in the areas of Final Fantasy
+        let mapped = data.game().is_mapped();
+        let location = data.location()?;
+        let location = self.location.update_infallible(location);
+        let (from, to) = (location.old, location.current);
+        if location.changed() {
+            // Going to or from the title screen is a reset, a new game, or
+            // a loaded save, the party does not walk between the maps
+            if from.id() == 0 || to.id() == 0 {
+                return None;
+            }
+
+            // The maps of the Citadel of Trials are not known yet, so the
+            // Rat's Tail is looked for once after leaving any unknown map
+            self.rats_tail_check = mapped && from.area().is_none();
+            return Some(Event::Moved { from, to });
+        }
+
+        if self.rats_tail_check {
+            self.rats_tail_check = false;
+            if data.key_item_ids().any(|item| item == Item::RatsTail)
+                && self.items.insert(&Item::RatsTail)
+            {
+                log!("Picked up the {:?}", Item::RatsTail);
+                return Some(Event::Obtained(Item::RatsTail));
+            }
+        }
+
+        if mapped && to.has_key_item() {
             if let Some(item) = self.inventory_check(data) {
-                return Some(match item {
-                    Item::Lute => SplitOn::Lute,
-                    Item::Ship => SplitOn::Ship,
-                    Item::Crown => SplitOn::Crown,
-                    Item::CrystalEye => SplitOn::CrystalEye,
-                    Item::Tonic => SplitOn::Tonic,
-                    Item::MysticKey => SplitOn::MysticKey,
-                    Item::Nitro => SplitOn::Nitro,
-                    Item::StarRuby => SplitOn::StarRuby,
-                    Item::EarthRod => SplitOn::EarthRod,
-                    Item::Canoe => SplitOn::Canoe,
-                    Item::LeviStone => SplitOn::LeviStone,
-                    Item::AirShip => SplitOn::AirShip,
-                    Item::WarpCube => SplitOn::WarpCube,
-                    Item::BottledFaerie => SplitOn::BottledFaerie,
-                    Item::Oxyale => SplitOn::Oxyale,
-                    Item::RosettaStone => SplitOn::RosettaStone,
-                    Item::Chime => SplitOn::Chime,
-                });
+                return Some(Event::Obtained(item));
             }
         }
 
         return None;
     }
 
-    fn battle_check(
+    fn class_check<G: GameState>(&mut self, data: &G) -> bool {
+        if data.game().is_mapped() == false {
+            return false;
+        }
+
+        // The jobs change one member at a time, and the party might not
+        // be readable at all. Only a full party with valid jobs counts.
+        let party = data.party();
+        if party.is_full() == false || party.iter().any(|c| c.job().is_none()) {
+            return false;
+        }
+
+        let promoted = party.iter().all(|c| c.job().is_some_and(Job::is_advanced));
+        let promoted = self.promoted.update_infallible(promoted);
+        if promoted.changed_to(&true) {
+            log!("Class change of the whole party");
+            return true;
+        }
+        return false;
+    }
+
+    fn battle_check<G: GameState>(
         &mut self,
-        data: &Data,
+        data: &G,
         split: BattleSplit,
     ) -> Option<Result<Monster, NoBattle>> {
         let in_battle = self.in_battle.update_infallible(data.battle_active());
@@ -714,17 +1736,37 @@ impl Splits {
             return Some(Err(NoBattle));
         }
 
+        // Counted when the battle is over, the result of the last battle
+        // might still be there when the next one starts
+        self.encounters.battle_result(data.battle_result());
+        if in_battle.changed_to(&false) {
+            self.encounters.battle_end();
+        }
+
         let monster = data.encounter()?;
 
         let result = data.battle_result();
         let result = self.battle_result.update_infallible(result);
 
         if in_battle.changed_to(&true) {
-            log!("Encounter: {monster:?} -- Started");
+            let boss = monster.is_boss();
+            match boss {
+                true => self.encounters.boss_fight(),
+                false => self.encounters.random_encounter(data.location()),
+            }
+            log!("Encounter: {monster:?} -- Started (boss: {boss})");
+            if let Some(formation) = data.formation() {
+                log!("Formation: {formation}");
+            }
             return None;
         }
 
         if in_battle.changed_to(&false) {
+            if result.current.is_lost() || result.old.is_lost() {
+                self.undo = self.tentative.take();
+            }
+            self.tentative = None;
+
             if result.changed_from(&BattleResult::Win) {
                 log!("Encounter: {monster:?} -- Ended");
                 if split == BattleSplit::BattleEnd {
@@ -743,8 +1785,12 @@ impl Splits {
         }
 
         if result.changed() {
-            let result = result.current;
-            log!("Encounter: {monster:?} -- {result:?}");
+            let current = result.current;
+            log!("Encounter: {monster:?} -- {current:?}");
+
+            if current.is_lost() {
+                self.undo = self.tentative.take();
+            }
         }
 
         if monster == Monster::Chaos {
@@ -778,17 +1824,47 @@ impl Splits {
         return None;
     }
 
-    fn field_check(&mut self, data: &Data) -> Option<Result<SplitOn, Location>> {
-        let location = data.location()?;
-        let location = self.location.update_infallible(location);
-        if let Some(field) = SplitOn::from_watcher(location) {
-            return Some(Ok(field));
+    /// Finds a key item that is not in the inventory anymore.
+    ///
+    /// A missing item is only reported when it is still missing on the
+    /// next tick, so that a partial read does not count as giving it away.
+    fn removal_check<G: GameState>(&mut self, data: &G) -> Option<Item> {
+        // The inventory is only compared again once a save is loaded
+        if self.title.is_some() {
+            return None;
+        }
+
+        let mut owned = Inventory::empty();
+        for item in data.key_item_ids() {
+            owned.insert(&item);
         }
 
-        Some(Err(location.current))
+        // Nothing at all is a failed read, the Lute is never given away
+        if owned.len() == 0 {
+            return None;
+        }
+
+        let removed = self.owned.iter().find(|item| owned.contains(item) == false);
+
+        match removed {
+            Some(item) if self.missing == Some(item) => {
+                log!("Gave away the {item:?}");
+                self.missing = None;
+                self.owned = owned;
+                return Some(item);
+            }
+            Some(item) => {
+                self.missing = Some(item);
+            }
+            None => {
+                self.missing = None;
+                self.owned = owned;
+            }
+        }
+        return None;
     }
 
-    fn inventory_check(&mut self, data: &Data) -> Option<Item> {
+    fn inventory_check<G: GameState>(&mut self, data: &G) -> Option<Item> {
         if let Some(item) = data.key_item_ids().find(|item| self.items.insert(item)) {
             log!("Picked up the {item:?}");
             return Some(item);
@@ -811,7 +1887,13 @@ impl core::fmt::Debug for SettingsDebug<'_> {
         let Settings {
             _general,
             start,
+            reset,
+            practice,
             battle_split,
+            ordered,
+            load_removal,
+            marathon,
+            record,
             _splits_heading1,
             _splits_heading2,
             _splits_heading3,
@@ -820,7 +1902,9 @@ impl core::fmt::Debug for SettingsDebug<'_> {
             pirates,
             ship,
             elfen_shop,
+            elfen_shop_count,
             marsh_cave,
+            marsh_cave_count,
             piscodemons,
             crown,
             astos,
@@ -829,48 +1913,85 @@ impl core::fmt::Debug for SettingsDebug<'_> {
             mystic_key,
             nitro,
             cornelia_shop,
+            cornelia_shop_count,
             firaga,
+            firaga_count,
             vampire,
             star_ruby,
             earth_rod,
             lich,
             crescent_lake_shop,
+            crescent_lake_shop_count,
             canoe,
             evil_eye,
             levi_stone,
             ice_cave,
+            ice_cave_count,
             air_ship,
+            rats_tail,
+            class_change,
             warp_cube,
             waterfall_cave,
+            waterfall_cave_count,
             bottled_faerie,
             gaia_shop,
+            gaia_shop_count,
             oxyale,
             onrac_shop,
+            onrac_shop_count,
             rosetta_stone,
             kraken,
             chime,
             blue_dragon,
             flying_fortress,
+            flying_fortress_count,
+            adamantite,
+            adamantite_trade,
             tiamat,
             marilith,
             death_eye,
             chaos_shrine,
+            chaos_shrine_count,
             lich2,
             marilith2,
             kraken2,
             tiamat2,
+            _used_heading,
+            crown_used,
+            crystal_eye_used,
+            tonic_used,
+            mystic_key_used,
+            nitro_used,
+            star_ruby_used,
+            earth_rod_used,
+            canoe_used,
+            levi_stone_used,
+            warp_cube_used,
+            bottled_faerie_used,
+            oxyale_used,
+            rosetta_stone_used,
+            chime_used,
+            rats_tail_used,
             chaos,
         } = self.0;
 
         f.debug_struct("Settings")
             .field("start", start)
+            .field("reset", reset)
+            .field("practice", practice)
             .field("battle_split", battle_split)
+            .field("ordered", ordered)
+            .field("load_removal", load_removal)
+            .field("marathon", marathon)
+            .field("record", record)
             .field("garland", garland)
             .field("lute", lute)
             .field("pirates", pirates)
             .field("ship", ship)
             .field("elfen_shop", elfen_shop)
+            .field("elfen_shop_count", elfen_shop_count)
             .field("marsh_cave", marsh_cave)
+            .field("marsh_cave_count", marsh_cave_count)
             .field("piscodemons", piscodemons)
             .field("crown", crown)
             .field("astos", astos)
@@ -879,36 +2000,64 @@ impl core::fmt::Debug for SettingsDebug<'_> {
             .field("mystic_key", mystic_key)
             .field("nitro", nitro)
             .field("cornelia_shop", cornelia_shop)
+            .field("cornelia_shop_count", cornelia_shop_count)
             .field("firaga", firaga)
+            .field("firaga_count", firaga_count)
             .field("vampire", vampire)
             .field("star_ruby", star_ruby)
             .field("earth_rod", earth_rod)
             .field("lich", lich)
             .field("crescent_lake_shop", crescent_lake_shop)
+            .field("crescent_lake_shop_count", crescent_lake_shop_count)
             .field("canoe", canoe)
             .field("evil_eye", evil_eye)
             .field("levi_stone", levi_stone)
             .field("ice_cave", ice_cave)
+            .field("ice_cave_count", ice_cave_count)
             .field("air_ship", air_ship)
+            .field("rats_tail", rats_tail)
+            .field("class_change", class_change)
             .field("warp_cube", warp_cube)
             .field("waterfall_cave", waterfall_cave)
+            .field("waterfall_cave_count", waterfall_cave_count)
             .field("bottled_faerie", bottled_faerie)
             .field("gaia_shop", gaia_shop)
+            .field("gaia_shop_count", gaia_shop_count)
             .field("oxyale", oxyale)
             .field("onrac_shop", onrac_shop)
+            .field("onrac_shop_count", onrac_shop_count)
             .field("rosetta_stone", rosetta_stone)
             .field("kraken", kraken)
             .field("chime", chime)
             .field("blue_dragon", blue_dragon)
             .field("flying_fortress", flying_fortress)
+            .field("flying_fortress_count", flying_fortress_count)
+            .field("adamantite", adamantite)
+            .field("adamantite_trade", adamantite_trade)
             .field("tiamat", tiamat)
             .field("marilith", marilith)
             .field("death_eye", death_eye)
             .field("chaos_shrine", chaos_shrine)
+            .field("chaos_shrine_count", chaos_shrine_count)
             .field("lich2", lich2)
             .field("marilith2", marilith2)
             .field("kraken2", kraken2)
             .field("tiamat2", tiamat2)
+            .field("crown_used", crown_used)
+            .field("crystal_eye_used", crystal_eye_used)
+            .field("tonic_used", tonic_used)
+            .field("mystic_key_used", mystic_key_used)
+            .field("nitro_used", nitro_used)
+            .field("star_ruby_used", star_ruby_used)
+            .field("earth_rod_used", earth_rod_used)
+            .field("canoe_used", canoe_used)
+            .field("levi_stone_used", levi_stone_used)
+            .field("warp_cube_used", warp_cube_used)
+            .field("bottled_faerie_used", bottled_faerie_used)
+            .field("oxyale_used", oxyale_used)
+            .field("rosetta_stone_used", rosetta_stone_used)
+            .field("chime_used", chime_used)
+            .field("rats_tail_used", rats_tail_used)
             .field("chaos", chaos)
             .finish()
     }
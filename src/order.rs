@@ -0,0 +1,171 @@
+//! The intended order of the splits, for the ordered route mode.
+//!
+//! The order is read from the `split_order` setting as a list of setting
+//! names, separated by commas or whitespace. Each entry is one segment,
+//! segments that are split manually are written as `-`. Map splits are
+//! written with their `enter_<map id>` or `leave_<map id>` keys.
+//!
+//! The setting is edited by hand, see the
+//! [hand-edited settings](../README.md#hand-edited-settings).
+//!
+//! A split that happens before its segment is queued in [`Pending`] and
+//! splits once the timer reaches its segment.
+
+use asr::{arrayvec::ArrayVec, settings, timer};
+
+use crate::{
+    data::{Item, ITEMS},
+    log,
+    route::RouteSplit,
+    transitions::Transition,
+    SplitOn,
+};
+
+const MAX_SEGMENTS: usize = 128;
+const MAX_PENDING: usize = 16;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Segment {
+    Split(SplitOn),
+    Transition(Transition),
+    Used(Item),
+    Route(RouteSplit),
+}
+
+#[derive(PartialEq, Eq)]
+pub struct SplitOrder {
+    segments: ArrayVec<Option<Segment>, MAX_SEGMENTS>,
+}
+
+impl SplitOrder {
+    pub const fn empty() -> Self {
+        Self {
+            segments: ArrayVec::new_const(),
+        }
+    }
+
+    /// Reads the order from the settings again and logs when it changed.
+    pub fn reload(&mut self) {
+        let order = Self::load();
+        if order != *self {
+            log!("Loaded split order with {} segments", order.segments.len());
+            *self = order;
+        }
+    }
+
+    fn load() -> Self {
+        let mut order = Self::empty();
+        let text = match settings::Map::load()
+            .get("split_order")
+            .and_then(|value| value.get_array_string::<4096>())
+        {
+            Some(Ok(text)) => text,
+            Some(Err(_)) => {
+                log!("Split order is longer than 4096 bytes, it is ignored");
+                return order;
+            }
+            None => return order,
+        };
+
+        for key in text
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|key| key.is_empty() == false)
+        {
+            let split = match key {
+                "-" => None,
+                key => {
+                    let split = SplitOn::from_key(key)
+                        .map(Segment::Split)
+                        .or_else(|| Transition::from_key(key).map(Segment::Transition))
+                        .or_else(|| used_from_key(key).map(Segment::Used));
+                    if split.is_none() {
+                        log!("Unknown split in split order: {key}");
+                    }
+                    split
+                }
+            };
+            if order.segments.try_push(split).is_err() {
+                log!("Split order has more than {MAX_SEGMENTS} segments");
+                break;
+            }
+        }
+
+        order
+    }
+
+    /// Whether the split belongs to the current segment.
+    ///
+    /// Without a configured order, every split is allowed, except for
+    /// route lines, which are the segments themselves.
+    pub fn allows(&self, split: Segment) -> bool {
+        if let Segment::Route(line) = split {
+            return current_segment() == Some(usize::from(line.0));
+        }
+
+        if self.segments.is_empty() {
+            return true;
+        }
+
+        let Some(index) = current_segment() else {
+            return false;
+        };
+
+        return self.segments.get(index) == Some(&Some(split));
+    }
+}
+
+/// Splits that happened before their segment came up.
+pub struct Pending {
+    segments: ArrayVec<Segment, MAX_PENDING>,
+}
+
+impl Pending {
+    pub const fn new() -> Self {
+        Self {
+            segments: ArrayVec::new_const(),
+        }
+    }
+
+    /// Keeps the split until its segment comes up.
+    pub fn push(&mut self, split: Segment) {
+        if self.segments.contains(&split) {
+            return;
+        }
+        if self.segments.try_push(split).is_err() {
+            log!(
+                "More than {MAX_PENDING} splits are waiting for their segment, dropping {split:?}"
+            );
+        }
+    }
+
+    /// Forgets the split, e.g. when it was undone before its segment.
+    pub fn remove(&mut self, split: Segment) {
+        self.segments.retain(|pending| *pending != split);
+    }
+
+    pub fn clear(&mut self) {
+        self.segments.clear();
+    }
+
+    /// Takes the waiting split that belongs to the current segment.
+    pub fn take_current(&mut self, order: &SplitOrder) -> Option<Segment> {
+        let index = self
+            .segments
+            .iter()
+            .position(|split| order.allows(*split))?;
+        return Some(self.segments.remove(index));
+    }
+}
+
+/// The item of a `<item>_used` key, e.g. `crown_used`.
+fn used_from_key(key: &str) -> Option<Item> {
+    let split = SplitOn::from_key(key.strip_suffix("_used")?)?;
+    ITEMS
+        .iter()
+        .copied()
+        .find(|item| SplitOn::from_item(*item) == split)
+}
+
+fn current_segment() -> Option<usize> {
+    usize::try_from(timer::current_split_index()?).ok()
+}
@@ -0,0 +1,222 @@
+//! User-defined routes, for splits that are not covered by the settings.
+//!
+//! The route is read from the `route` setting, one condition per line.
+//! Every line is one segment, in the order of the splits:
+//!
+//! ```text
+//! # comments and empty lines are ignored
+//! battle_won 350
+//! formation 88,88,88
+//! obtain 44
+//! enter 12
+//! leave 12 to 1
+//! leave 13
+//! use 49
+//! ff2 enter 12
+//! -
+//! ```
+//!
+//! Monsters, maps and items are given by their ids. `battle_won` only looks
+//! at the monster in the first slot, `formation` at all monsters of the
+//! battle, in any order. `leave` without a destination matches leaving to
+//! any map, and `-` is a segment that is split manually.
+//!
+//! The ids are only meaningful for one game. During a marathon, a line can
+//! be prefixed with the game it belongs to, e.g. `ff2`, and then only
+//! matches in that game. Lines without a game match in every game.
+//!
+//! The setting is edited by hand, see the
+//! [hand-edited settings](../README.md#hand-edited-settings).
+
+use asr::{arrayvec::ArrayVec, settings};
+
+use crate::{
+    data::{Formation, Game, Monster},
+    log, EnumSet, EnumSetMember, Event,
+};
+
+const MAX_LINES: usize = 64;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Condition {
+    BattleWon(u32),
+    Formation(Formation),
+    Enter(u32),
+    Leave { from: u32, to: Option<u32> },
+    Obtain(u32),
+    Use(u32),
+}
+
+impl Condition {
+    fn parse(line: &str) -> Option<Self> {
+        let mut words = line.split_whitespace();
+        let condition = match words.next()? {
+            "battle_won" => Self::BattleWon(words.next()?.parse().ok()?),
+            "formation" => {
+                let ids = words.next()?;
+                let formation = ids
+                    .split(',')
+                    .map(|id| id.parse::<u32>().ok().map(Monster::from))
+                    .collect::<Option<Formation>>()?;
+                // More monsters than slots could never match
+                if formation.monsters().len() != ids.split(',').count() {
+                    return None;
+                }
+                Self::Formation(formation)
+            }
+            "enter" => Self::Enter(words.next()?.parse().ok()?),
+            "leave" => {
+                let from = words.next()?.parse().ok()?;
+                let to = match words.next() {
+                    Some("to") => Some(words.next()?.parse().ok()?),
+                    Some(_) => return None,
+                    None => None,
+                };
+                Self::Leave { from, to }
+            }
+            "obtain" => Self::Obtain(words.next()?.parse().ok()?),
+            "use" => Self::Use(words.next()?.parse().ok()?),
+            _ => return None,
+        };
+
+        if words.next().is_some() {
+            return None;
+        }
+        Some(condition)
+    }
+
+    fn matches(self, event: Event) -> bool {
+        match (self, event) {
+            (Self::BattleWon(id), Event::BattleWon { monster, .. }) => monster.id() == id,
+            (Self::Formation(expected), Event::BattleWon { formation, .. }) => {
+                formation.is_some_and(|formation| formation.same_monsters(&expected))
+            }
+            (Self::Enter(id), Event::Moved { to, .. }) => to.id() == id,
+            (Self::Leave { from, to }, Event::Moved { from: old, to: new }) => {
+                old.id() == from && to.is_none_or(|to| new.id() == to)
+            }
+            (Self::Obtain(id), Event::Obtained(item)) => u32::from(item) == id,
+            (Self::Use(id), Event::Used(item)) => u32::from(item) == id,
+            _ => false,
+        }
+    }
+}
+
+/// A condition, optionally only for one of the games.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Line {
+    game: Option<Game>,
+    condition: Condition,
+}
+
+impl Line {
+    fn parse(line: &str) -> Option<Self> {
+        let (first, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        match Game::from_key(first) {
+            Some(game) => Some(Self {
+                game: Some(game),
+                condition: Condition::parse(rest)?,
+            }),
+            None => Some(Self {
+                game: None,
+                condition: Condition::parse(line)?,
+            }),
+        }
+    }
+
+    fn matches(self, game: Game, event: Event) -> bool {
+        self.game.is_none_or(|only| only == game) && self.condition.matches(event)
+    }
+}
+
+/// A line of the route, counted from 0.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RouteSplit(pub u8);
+
+impl EnumSetMember for RouteSplit {
+    fn ordinal(&self) -> Option<u16> {
+        Some(u16::from(self.0))
+    }
+
+    fn from_ordinal(ordinal: u16) -> Option<Self> {
+        u8::try_from(ordinal).ok().map(Self)
+    }
+}
+
+pub type SeenRoute = EnumSet<RouteSplit>;
+
+#[derive(PartialEq, Eq)]
+pub struct Route {
+    lines: ArrayVec<Option<Line>, MAX_LINES>,
+}
+
+impl Route {
+    pub const fn empty() -> Self {
+        Self {
+            lines: ArrayVec::new_const(),
+        }
+    }
+
+    /// Reads the route from the settings again and logs when it changed.
+    pub fn reload(&mut self) {
+        let route = Self::load();
+        if route != *self {
+            log!("Loaded route with {} lines", route.lines.len());
+            *self = route;
+        }
+    }
+
+    fn load() -> Self {
+        match settings::Map::load()
+            .get("route")
+            .and_then(|value| value.get_array_string::<4096>())
+        {
+            Some(Ok(text)) => Self::parse(&text),
+            Some(Err(_)) => {
+                log!("Route is longer than 4096 bytes, it is ignored");
+                Self::empty()
+            }
+            None => Self::empty(),
+        }
+    }
+
+    pub fn parse(text: &str) -> Self {
+        let mut route = Self::empty();
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let parsed = match line {
+                "-" => None,
+                line => {
+                    // Invalid lines are kept as manual segments,
+                    // so that the following lines stay in order.
+                    let parsed = Line::parse(line);
+                    if parsed.is_none() {
+                        log!("Invalid route line {}: {line}", number + 1);
+                    }
+                    parsed
+                }
+            };
+            if route.lines.try_push(parsed).is_err() {
+                log!("Route has more than {MAX_LINES} lines");
+                break;
+            }
+        }
+
+        route
+    }
+
+    /// The first line that matches the event in the game and has not been split yet.
+    pub fn find(&self, game: Game, event: Event, seen: &SeenRoute) -> Option<RouteSplit> {
+        self.lines
+            .iter()
+            .enumerate()
+            .filter_map(|(index, line)| Some((RouteSplit(index as u8), (*line)?)))
+            .find(|(split, line)| seen.contains(split) == false && line.matches(game, event))
+            .map(|(split, _)| split)
+    }
+}
@@ -0,0 +1,201 @@
+//! A scripted stand-in for the game, so that the split logic can be
+//! driven frame by frame without attaching to a process.
+
+use asr::timer::TimerState;
+use core::ops::ControlFlow;
+
+use crate::{
+    data::{BattleResult, Formation, Game, GameState, Item, Location, Party},
+    main_loop,
+    route::Route,
+    stats::Encounters,
+    transitions::{MapSplits, Transition},
+    Action, BattleSplit, Counters, Occurrence, Rules, SplitOn, Splits, State, Title,
+};
+
+/// The values that the game would report during a single tick.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame {
+    pub game: Game,
+    pub battle_active: bool,
+    pub formation: Option<Formation>,
+    pub battle_result: BattleResult,
+    pub battle_time: f32,
+    pub key_items: Vec<Item>,
+    pub vehicles: Vec<Item>,
+    pub map_id: Option<u32>,
+    pub fade_out: bool,
+    pub loading: bool,
+    pub party: Party,
+}
+
+impl Default for Frame {
+    fn default() -> Self {
+        Self {
+            game: Game::FinalFantasy1,
+            battle_active: false,
+            formation: None,
+            battle_result: BattleResult::None,
+            battle_time: 0.0,
+            key_items: Vec::new(),
+            vehicles: Vec::new(),
+            map_id: None,
+            fade_out: false,
+            loading: false,
+            party: Party::new(),
+        }
+    }
+}
+
+impl GameState for Frame {
+    fn game(&self) -> Game {
+        self.game
+    }
+
+    fn battle_active(&self) -> bool {
+        self.battle_active
+    }
+
+    fn formation(&self) -> Option<Formation> {
+        self.formation
+    }
+
+    fn battle_result(&self) -> BattleResult {
+        self.battle_result
+    }
+
+    fn battle_time(&self) -> f32 {
+        self.battle_time
+    }
+
+    fn key_item_ids(&self) -> impl Iterator<Item = Item> + '_ {
+        self.key_items.iter().copied()
+    }
+
+    fn vehicle_ids(&self) -> impl Iterator<Item = Item> + '_ {
+        self.vehicles.iter().copied()
+    }
+
+    fn map_id(&self) -> Option<u32> {
+        self.map_id
+    }
+
+    fn has_fade_out(&self) -> bool {
+        self.fade_out
+    }
+
+    fn is_loading(&self) -> bool {
+        self.loading
+    }
+
+    fn party(&self) -> &Party {
+        &self.party
+    }
+}
+
+/// Runs frames through the same state machine as the game loop.
+///
+/// The timer is simulated, it starts running on the first
+/// [`Action::Start`] and stays running afterwards.
+pub struct Script {
+    state: State,
+    timer: TimerState,
+    battle_split: BattleSplit,
+    route: Route,
+    transitions: MapSplits,
+    occurrences: Counters,
+}
+
+impl Script {
+    pub fn new(battle_split: BattleSplit) -> Self {
+        Self {
+            state: State::NotRunning(Title::new()),
+            timer: TimerState::NotRunning,
+            battle_split,
+            route: Route::empty(),
+            transitions: MapSplits::empty(),
+            occurrences: Counters::filled(Occurrence::First.count()),
+        }
+    }
+
+    /// The timer is already running, like when attaching to a run in progress.
+    pub fn running(battle_split: BattleSplit) -> Self {
+        Self {
+            timer: TimerState::Running,
+            ..Self::new(battle_split)
+        }
+    }
+
+    /// Uses the route for all following frames.
+    pub fn with_route(mut self, route: &str) -> Self {
+        self.route = Route::parse(route);
+        self
+    }
+
+    /// Splits when entering or leaving the map.
+    pub fn with_transition(mut self, transition: Transition) -> Self {
+        self.transitions.insert(transition);
+        self
+    }
+
+    /// Only splits on the given visit of the map for map splits.
+    pub fn with_map_occurrence(mut self, location: Location, occurrence: Occurrence) -> Self {
+        self.transitions.set_occurrence(location, occurrence);
+        self
+    }
+
+    /// Only splits on the given visit of a location split.
+    pub fn with_occurrence(mut self, split: SplitOn, occurrence: Occurrence) -> Self {
+        self.occurrences.set(split, occurrence.count());
+        self
+    }
+
+    /// Switches to the next game of a marathon, like attaching to it.
+    pub fn next_game(&mut self, frame: &Frame) {
+        if let State::Running(splits) = &mut self.state {
+            splits.next_game();
+            splits.seed(frame);
+        }
+    }
+
+    /// The encounter statistics, once the run is started.
+    pub fn encounters(&self) -> Option<&Encounters> {
+        match &self.state {
+            State::Running(splits) => Some(&splits.encounters),
+            State::NotRunning(_) => None,
+        }
+    }
+
+    /// Feeds a single frame and returns the resulting action.
+    pub fn step(&mut self, frame: &Frame) -> Action {
+        loop {
+            let rules = Rules {
+                battle_split: self.battle_split,
+                route: &self.route,
+                transitions: &self.transitions,
+                occurrences: self.occurrences,
+            };
+            match main_loop(frame, &mut self.state, self.timer, &rules) {
+                ControlFlow::Continue(()) => continue,
+                ControlFlow::Break(action) => {
+                    if matches!(action, Action::Start | Action::Reset) {
+                        self.timer = TimerState::Running;
+                    }
+                    if action == Action::Reset {
+                        self.state = State::Running(Splits::new());
+                    }
+                    return action;
+                }
+            }
+        }
+    }
+
+    /// Feeds all frames and returns every action that is not [`Action::None`].
+    pub fn run<'a>(&mut self, frames: impl IntoIterator<Item = &'a Frame>) -> Vec<Action> {
+        frames
+            .into_iter()
+            .map(|frame| self.step(frame))
+            .filter(|action| *action != Action::None)
+            .collect()
+    }
+}
@@ -0,0 +1,78 @@
+//! Encounter statistics of the current run.
+
+use asr::arrayvec::ArrayVec;
+
+use crate::data::{BattleResult, Location};
+
+const MAX_MAPS: usize = 128;
+
+#[derive(Clone, Debug)]
+pub struct Encounters {
+    /// Random encounters, i.e. battles that are not a known boss fight.
+    pub total: u32,
+    pub escapes: u32,
+    pub wins: u32,
+    per_map: ArrayVec<(Location, u32), MAX_MAPS>,
+    /// The last result of the random encounter in progress.
+    battle: Option<BattleResult>,
+}
+
+impl Encounters {
+    pub const fn new() -> Self {
+        Self {
+            total: 0,
+            escapes: 0,
+            wins: 0,
+            per_map: ArrayVec::new_const(),
+            battle: None,
+        }
+    }
+
+    pub fn random_encounter(&mut self, location: Option<Location>) {
+        self.total += 1;
+        self.battle = Some(BattleResult::None);
+
+        let Some(location) = location else {
+            return;
+        };
+        match self.per_map.iter_mut().find(|(map, _)| *map == location) {
+            Some((_, count)) => *count += 1,
+            // Maps beyond the capacity are only part of the total
+            None => {
+                let _ = self.per_map.try_push((location, 1));
+            }
+        }
+    }
+
+    /// Boss fights are not random encounters, their result is not counted.
+    pub fn boss_fight(&mut self) {
+        self.battle = None;
+    }
+
+    pub fn battle_result(&mut self, result: BattleResult) {
+        // A cleared result does not take back the result of this battle
+        if result == BattleResult::None {
+            return;
+        }
+        if let Some(battle) = &mut self.battle {
+            *battle = result;
+        }
+    }
+
+    /// Counts the last result of the battle, once per battle.
+    pub fn battle_end(&mut self) {
+        match self.battle.take() {
+            Some(BattleResult::Win) => self.wins += 1,
+            Some(BattleResult::Escape) => self.escapes += 1,
+            _ => {}
+        }
+    }
+
+    /// The random encounters on the map.
+    pub fn at(&self, location: Location) -> u32 {
+        self.per_map
+            .iter()
+            .find(|(map, _)| *map == location)
+            .map_or(0, |(_, count)| *count)
+    }
+}
@@ -0,0 +1,857 @@
+//! Tests of the split logic, driven frame by frame through the [`Script`].
+
+use asr::arrayvec::ArrayString;
+
+use crate::{
+    data::{maps, BattleResult, Character, Formation, Game, Item, Job, Location, Monster, Party},
+    route::{Route, RouteSplit, SeenRoute},
+    script::{Frame, Script},
+    trace::{
+        replay::{parse_line, replay},
+        write_frame,
+    },
+    transitions::Transition,
+    Action, BattleSplit, EnumSet, EnumSetMember, Event, Occurrence, SplitOn,
+};
+
+/// The title screen, before a new game is started or a save is loaded.
+fn title() -> Frame {
+    Frame {
+        map_id: Some(0),
+        ..Frame::default()
+    }
+}
+
+fn on_map(location: Location) -> Frame {
+    Frame {
+        map_id: Some(location.id()),
+        ..Frame::default()
+    }
+}
+
+fn with_items(location: Location, key_items: &[Item]) -> Frame {
+    Frame {
+        key_items: key_items.to_vec(),
+        ..on_map(location)
+    }
+}
+
+fn formation(monsters: &[Monster]) -> Option<Formation> {
+    Some(monsters.iter().copied().collect())
+}
+
+/// A battle against the monster on the world map.
+fn battle(monster: Monster, result: BattleResult) -> Frame {
+    Frame {
+        battle_active: true,
+        formation: formation(&[monster]),
+        battle_result: result,
+        ..on_map(Location::WorldMap)
+    }
+}
+
+/// A battle against all the monsters on the world map.
+fn battle_with(monsters: &[Monster], result: BattleResult) -> Frame {
+    Frame {
+        formation: formation(monsters),
+        ..battle(monsters[0], result)
+    }
+}
+
+/// Back on the world map, the formation of the last battle is still there.
+fn after_battle(monster: Monster) -> Frame {
+    Frame {
+        formation: formation(&[monster]),
+        ..on_map(Location::WorldMap)
+    }
+}
+
+/// A run that has just been started on the world map.
+fn new_game(battle_split: BattleSplit) -> Script {
+    let mut script = Script::new(battle_split);
+    let frames = [
+        title(),
+        Frame {
+            fade_out: true,
+            ..title()
+        },
+        on_map(Location::WorldMap),
+    ];
+    assert_eq!(script.run(&frames), [Action::Start]);
+    script
+}
+
+#[test]
+fn starts_on_party_confirmation() {
+    let mut script = Script::new(BattleSplit::BattleEnd);
+    let fade_out = Frame {
+        fade_out: true,
+        ..title()
+    };
+
+    assert_eq!(script.step(&title()), Action::None);
+    assert_eq!(script.step(&fade_out), Action::Start);
+    // The fade out lasts for a while, but only starts once
+    assert_eq!(script.step(&fade_out), Action::None);
+    assert_eq!(script.step(&on_map(Location::WorldMap)), Action::None);
+}
+
+#[test]
+fn loading_a_save_does_not_start() {
+    let mut script = Script::new(BattleSplit::BattleEnd);
+
+    assert_eq!(script.step(&title()), Action::None);
+    assert_eq!(script.step(&on_map(Location::Cornelia)), Action::Load);
+}
+
+#[test]
+fn splits_bosses_after_the_battle() {
+    let mut script = new_game(BattleSplit::BattleEnd);
+
+    assert_eq!(
+        script.step(&battle(Monster::Garland, BattleResult::None)),
+        Action::None
+    );
+    assert_eq!(
+        script.step(&battle(Monster::Garland, BattleResult::Win)),
+        Action::None
+    );
+    assert_eq!(
+        script.step(&after_battle(Monster::Garland)),
+        Action::Split(SplitOn::Garland)
+    );
+    assert_eq!(script.step(&after_battle(Monster::Garland)), Action::None);
+}
+
+#[test]
+fn splits_bosses_on_the_death_animation() {
+    let mut script = new_game(BattleSplit::DeathAnimation);
+
+    assert_eq!(
+        script.step(&battle(Monster::Garland, BattleResult::None)),
+        Action::None
+    );
+    assert_eq!(
+        script.step(&battle(Monster::Garland, BattleResult::Win)),
+        Action::Split(SplitOn::Garland)
+    );
+    assert_eq!(script.step(&after_battle(Monster::Garland)), Action::None);
+}
+
+#[test]
+fn undoes_the_death_animation_split_when_the_battle_is_lost() {
+    let mut script = new_game(BattleSplit::DeathAnimation);
+
+    let frames = [
+        battle(Monster::Garland, BattleResult::None),
+        battle(Monster::Garland, BattleResult::Win),
+        battle(Monster::Garland, BattleResult::Lose),
+    ];
+    assert_eq!(
+        script.run(&frames),
+        [
+            Action::Split(SplitOn::Garland),
+            Action::Undo(SplitOn::Garland)
+        ]
+    );
+}
+
+#[test]
+fn regular_encounters_do_not_split() {
+    let mut script = new_game(BattleSplit::DeathAnimation);
+    let goblin = Monster::Unknown(1);
+
+    let frames = [
+        battle(goblin, BattleResult::None),
+        battle(goblin, BattleResult::Win),
+        after_battle(goblin),
+    ];
+    assert_eq!(script.run(&frames), [Action::None; 0]);
+}
+
+#[test]
+fn only_the_boss_table_makes_a_boss() {
+    assert!(Monster::Garland.is_boss());
+    assert!(Monster::Chaos.is_boss());
+    assert!(Monster::Piscodemons.is_boss());
+    assert!(Monster::Unknown(1).is_boss() == false);
+}
+
+#[test]
+fn areas_do_not_overlap() {
+    let ids = maps().map(Location::id);
+    assert!(ids.clone().zip(ids.skip(1)).all(|(a, b)| a < b));
+}
+
+#[test]
+fn maps_that_are_not_identified_have_no_area() {
+    let gaps = [
+        49..=51,
+        68..=69,
+        71..=72,
+        81..=87,
+        92..=98,
+        116..=121,
+        123..=255,
+    ];
+    for id in gaps.into_iter().flatten() {
+        let location = Location::from(id);
+        assert_eq!(location, Location::Unknown(id));
+        assert!(location.area().is_none(), "map {id} has an area");
+    }
+    assert_eq!(maps().count(), 95);
+}
+
+#[test]
+fn counts_every_won_random_encounter() {
+    let mut script = new_game(BattleSplit::BattleEnd);
+    let goblin = Monster::Unknown(1);
+
+    let frames = [
+        battle(goblin, BattleResult::None),
+        battle(goblin, BattleResult::Win),
+        after_battle(goblin),
+        // The result of the last battle is still there
+        battle(goblin, BattleResult::Win),
+        battle(goblin, BattleResult::Win),
+        after_battle(goblin),
+        battle(Monster::Garland, BattleResult::None),
+        battle(Monster::Garland, BattleResult::Win),
+        after_battle(Monster::Garland),
+    ];
+    assert_eq!(script.run(&frames), [Action::Split(SplitOn::Garland)]);
+
+    let encounters = script.encounters().unwrap();
+    assert_eq!(encounters.total, 2);
+    assert_eq!(encounters.wins, 2);
+    assert_eq!(encounters.escapes, 0);
+}
+
+#[test]
+fn picks_up_key_items_only_where_they_are() {
+    let mut script = new_game(BattleSplit::BattleEnd);
+
+    // Cornelia has no key item, the Lute is not checked there
+    let frames = [
+        with_items(Location::Cornelia, &[Item::Lute]),
+        with_items(Location::Cornelia, &[Item::Lute]),
+    ];
+    assert_eq!(script.run(&frames), [Action::None; 0]);
+
+    // Entering the map is one tick, the inventory is checked on the next one
+    let frames = [
+        with_items(Location::CorneliaThrone, &[Item::Lute]),
+        with_items(Location::CorneliaThrone, &[Item::Lute]),
+        with_items(Location::CorneliaThrone, &[Item::Lute]),
+    ];
+    assert_eq!(script.run(&frames), [Action::Split(SplitOn::Lute)]);
+}
+
+#[test]
+fn splits_when_entering_the_marsh_cave() {
+    let mut script = new_game(BattleSplit::BattleEnd);
+
+    assert_eq!(
+        script.step(&on_map(Location::MarshCave1)),
+        Action::Split(SplitOn::EnterMarshCave)
+    );
+    // Only the first visit splits
+    assert_eq!(script.step(&on_map(Location::WorldMap)), Action::None);
+    assert_eq!(script.step(&on_map(Location::MarshCave1)), Action::None);
+}
+
+#[test]
+fn splits_on_the_selected_visit() {
+    let mut script = new_game(BattleSplit::BattleEnd)
+        .with_occurrence(SplitOn::EnterMarshCave, Occurrence::Second);
+
+    let frames = [
+        on_map(Location::MarshCave1),
+        on_map(Location::WorldMap),
+        on_map(Location::MarshCave1),
+        on_map(Location::WorldMap),
+        on_map(Location::MarshCave1),
+    ];
+    assert_eq!(
+        script.run(&frames),
+        [Action::Split(SplitOn::EnterMarshCave)]
+    );
+}
+
+#[test]
+fn splits_on_selected_map_transitions() {
+    let leave = Transition::Leave(Location::Cornelia);
+    let mut script = new_game(BattleSplit::BattleEnd).with_transition(leave);
+
+    assert_eq!(script.step(&on_map(Location::Cornelia)), Action::None);
+    assert_eq!(
+        script.step(&on_map(Location::WorldMap)),
+        Action::Transition(leave)
+    );
+    assert_eq!(script.step(&on_map(Location::Cornelia)), Action::None);
+    assert_eq!(script.step(&on_map(Location::WorldMap)), Action::None);
+}
+
+#[test]
+fn splits_on_route_lines() {
+    let mut script = new_game(BattleSplit::BattleEnd).with_route("enter 4\nbattle_won 350");
+
+    assert_eq!(
+        script.step(&on_map(Location::Cornelia)),
+        Action::Route(RouteSplit(0))
+    );
+    let frames = [
+        battle(Monster::Garland, BattleResult::None),
+        battle(Monster::Garland, BattleResult::Win),
+        after_battle(Monster::Garland),
+    ];
+    assert_eq!(script.run(&frames), [Action::Route(RouteSplit(1))]);
+}
+
+#[test]
+fn trace_lines_read_back_as_the_same_frame() {
+    let frames = [
+        Frame::default(),
+        title(),
+        Frame {
+            battle_active: true,
+            formation: formation(&[Monster::Piscodemons, Monster::Piscodemons]),
+            battle_result: BattleResult::Win,
+            battle_time: 12.5,
+            key_items: vec![Item::Lute, Item::Crown],
+            vehicles: vec![Item::Ship],
+            ..on_map(Location::MarshCave3)
+        },
+        // All slots are empty, which is not the same as no formation at all
+        Frame {
+            formation: formation(&[]),
+            ..on_map(Location::WorldMap)
+        },
+        party([Job::Knight, Job::Thief, Job::WhiteMage, Job::BlackWizard]),
+        Frame {
+            game: Game::FinalFantasy2,
+            ..on_map(Location::Unknown(12))
+        },
+    ];
+
+    for frame in frames {
+        let mut line = String::new();
+        write_frame(&mut line, &frame).unwrap();
+        let (tick, parsed) = parse_line(&format!("@trace 42 {line}")).unwrap();
+        assert_eq!(tick, 42);
+        assert_eq!(parsed, frame, "{line}");
+    }
+}
+
+#[test]
+fn replays_the_class_change() {
+    let trace = "\
+        @trace 0 ff1 0 - 0 0.00 0 0 0 - - -
+        @trace 120 ff1 0 - 0 0.00 0 1 1 - - -
+        @trace 180 ff1 0 - 0 0.00 1 0 0 - - Ann:1:30,Bob:2:30,Cid:5:30,Dan:6:30
+        @trace 600 ff1 0 - 0 0.00 1 0 0 - - Ann:7:30,Bob:2:30,Cid:5:30,Dan:6:30
+        @trace 601 ff1 0 - 0 0.00 1 0 0 - - Ann:7:30,Bob:8:30,Cid:5:30,Dan:6:30
+        @trace 602 ff1 0 - 0 0.00 1 0 0 - - Ann:7:30,Bob:8:30,Cid:11:30,Dan:6:30
+        @trace 603 ff1 0 - 0 0.00 1 0 0 - - Ann:7:30,Bob:8:30,Cid:11:30,Dan:12:30
+        @trace 900 ff1 0 - 0 0.00 1 0 0 - - Ann:7:30,Bob:8:30,Cid:11:30,Dan:12:30
+    ";
+    let expected = [Action::Start, Action::Split(SplitOn::ClassChange)];
+
+    assert_eq!(replay(trace, BattleSplit::BattleEnd), expected);
+}
+
+#[test]
+fn replays_the_start_of_a_run() {
+    let trace = include_str!("../traces/new_game.trace");
+    let expected = [
+        Action::Start,
+        Action::Split(SplitOn::Garland),
+        Action::Split(SplitOn::Lute),
+    ];
+
+    assert_eq!(replay(trace, BattleSplit::BattleEnd), expected);
+    assert_eq!(replay(trace, BattleSplit::DeathAnimation), expected);
+}
+
+#[test]
+fn route_formations_match_all_monsters() {
+    let mut script = new_game(BattleSplit::DeathAnimation).with_route("formation 88,88,88");
+    let piscodemon = Monster::Piscodemons;
+    let ogre = Monster::Unknown(20);
+
+    // The lead monster is the same, but the group is not
+    let frames = [
+        battle_with(&[piscodemon, piscodemon, ogre], BattleResult::None),
+        battle_with(&[piscodemon, piscodemon, ogre], BattleResult::Win),
+        after_battle(piscodemon),
+    ];
+    assert_eq!(script.run(&frames), [Action::Split(SplitOn::Piscodemons)]);
+
+    let frames = [
+        battle_with(&[piscodemon, piscodemon, piscodemon], BattleResult::None),
+        battle_with(&[piscodemon, piscodemon, piscodemon], BattleResult::Win),
+        after_battle(piscodemon),
+    ];
+    assert_eq!(script.run(&frames), [Action::Route(RouteSplit(0))]);
+}
+
+#[test]
+fn failed_reads_are_not_the_title_screen() {
+    let mut script = new_game(BattleSplit::BattleEnd);
+    let failed = Frame {
+        map_id: None,
+        ..Frame::default()
+    };
+
+    // A failed read must not look like returning to the title screen and
+    // loading a save, which would restart the timer in practice mode
+    let frames = [
+        on_map(Location::Cornelia),
+        failed.clone(),
+        failed,
+        on_map(Location::Cornelia),
+    ];
+    assert_eq!(script.run(&frames), [Action::None; 0]);
+}
+
+fn moved(from: Location, to: Location) -> Event {
+    Event::Moved { from, to }
+}
+
+#[test]
+fn route_lines_skip_comments_and_empty_lines() {
+    let route = Route::parse("# Cornelia first\n\nenter 4 # the town\n   \nenter 12\n");
+    let seen = SeenRoute::empty();
+
+    let into_town = moved(Location::WorldMap, Location::Cornelia);
+    assert_eq!(
+        route.find(Game::FinalFantasy1, into_town, &seen),
+        Some(RouteSplit(0))
+    );
+    let into_cave = moved(Location::WorldMap, Location::MatoyaCave);
+    assert_eq!(
+        route.find(Game::FinalFantasy1, into_cave, &seen),
+        Some(RouteSplit(1))
+    );
+}
+
+#[test]
+fn manual_route_lines_keep_their_segment() {
+    let route = Route::parse("-\n-\nenter 4");
+    let seen = SeenRoute::empty();
+
+    let into_town = moved(Location::WorldMap, Location::Cornelia);
+    assert_eq!(
+        route.find(Game::FinalFantasy1, into_town, &seen),
+        Some(RouteSplit(2))
+    );
+}
+
+#[test]
+fn invalid_route_lines_keep_their_segment_and_never_split() {
+    let route = Route::parse("enter\nenter four\nenter 4 to 1\nvisit 4\nenter 4");
+    let seen = SeenRoute::empty();
+
+    let into_town = moved(Location::WorldMap, Location::Cornelia);
+    assert_eq!(
+        route.find(Game::FinalFantasy1, into_town, &seen),
+        Some(RouteSplit(4))
+    );
+}
+
+#[test]
+fn route_lines_leave_towards_a_map() {
+    let route = Route::parse("leave 12 to 4\nleave 12");
+    let seen = SeenRoute::empty();
+
+    let to_town = moved(Location::MatoyaCave, Location::Cornelia);
+    assert_eq!(
+        route.find(Game::FinalFantasy1, to_town, &seen),
+        Some(RouteSplit(0))
+    );
+    let to_world = moved(Location::MatoyaCave, Location::WorldMap);
+    assert_eq!(
+        route.find(Game::FinalFantasy1, to_world, &seen),
+        Some(RouteSplit(1))
+    );
+    let into_cave = moved(Location::WorldMap, Location::MatoyaCave);
+    assert_eq!(route.find(Game::FinalFantasy1, into_cave, &seen), None);
+}
+
+#[test]
+fn route_lines_split_once() {
+    let route = Route::parse("enter 4\nenter 4");
+    let mut seen = SeenRoute::empty();
+    let into_town = moved(Location::WorldMap, Location::Cornelia);
+
+    seen.insert(&RouteSplit(0));
+    assert_eq!(
+        route.find(Game::FinalFantasy1, into_town, &seen),
+        Some(RouteSplit(1))
+    );
+    seen.insert(&RouteSplit(1));
+    assert_eq!(route.find(Game::FinalFantasy1, into_town, &seen), None);
+}
+
+#[test]
+fn splits_on_maps_without_a_name() {
+    // A shop in Cornelia that has no name yet
+    let shop = Location::Unknown(7);
+    assert_eq!(
+        Transition::from_key("enter_7"),
+        Some(Transition::Enter(shop))
+    );
+    assert_eq!(Transition::from_key("enter_50"), None);
+
+    let enter = Transition::Enter(shop);
+    let mut script = new_game(BattleSplit::BattleEnd).with_transition(enter);
+
+    assert_eq!(script.step(&on_map(Location::Cornelia)), Action::None);
+    assert_eq!(script.step(&on_map(shop)), Action::Transition(enter));
+}
+
+#[test]
+fn splits_on_the_selected_visit_of_a_map() {
+    let leave = Transition::Leave(Location::Cornelia);
+    let mut script = new_game(BattleSplit::BattleEnd)
+        .with_transition(leave)
+        .with_map_occurrence(Location::Cornelia, Occurrence::Third);
+
+    let frames = [
+        on_map(Location::Cornelia),
+        on_map(Location::WorldMap),
+        on_map(Location::Cornelia),
+        on_map(Location::WorldMap),
+        on_map(Location::Cornelia),
+    ];
+    assert_eq!(script.run(&frames), [Action::None; 0]);
+    assert_eq!(
+        script.step(&on_map(Location::WorldMap)),
+        Action::Transition(leave)
+    );
+    assert_eq!(script.step(&on_map(Location::Cornelia)), Action::None);
+    assert_eq!(script.step(&on_map(Location::WorldMap)), Action::None);
+}
+
+#[test]
+fn route_lines_only_match_in_their_game() {
+    let route = Route::parse("ff2 enter 4\nff1 enter 4\nenter 4");
+    let seen = SeenRoute::empty();
+    let into_town = moved(Location::WorldMap, Location::Cornelia);
+
+    assert_eq!(
+        route.find(Game::FinalFantasy2, into_town, &seen),
+        Some(RouteSplit(0))
+    );
+    assert_eq!(
+        route.find(Game::FinalFantasy1, into_town, &seen),
+        Some(RouteSplit(1))
+    );
+    assert_eq!(
+        route.find(Game::FinalFantasy3, into_town, &seen),
+        Some(RouteSplit(2))
+    );
+}
+
+#[test]
+fn map_splits_are_only_for_final_fantasy() {
+    let enter = Transition::Enter(Location::Cornelia);
+    let mut script = new_game(BattleSplit::BattleEnd).with_transition(enter);
+    let in_ff2 = |frame: Frame| Frame {
+        game: Game::FinalFantasy2,
+        ..frame
+    };
+
+    // Map 4 of Final Fantasy II is not Cornelia
+    let frames = [
+        in_ff2(on_map(Location::WorldMap)),
+        in_ff2(on_map(Location::Cornelia)),
+    ];
+    assert_eq!(script.run(&frames), [Action::None; 0]);
+}
+
+#[test]
+fn key_items_are_only_for_final_fantasy() {
+    let mut script = new_game(BattleSplit::BattleEnd);
+    let in_ff2 = |frame: Frame| Frame {
+        game: Game::FinalFantasy2,
+        ..frame
+    };
+
+    // Map 109 of Final Fantasy II is not the Flying Fortress
+    let fortress = Location::Unknown(109);
+    let frames = [
+        in_ff2(on_map(Location::WorldMap)),
+        in_ff2(on_map(fortress)),
+        in_ff2(with_items(fortress, &[Item::Adamantite])),
+        in_ff2(with_items(fortress, &[Item::Adamantite])),
+    ];
+    assert_eq!(script.run(&frames), [Action::None; 0]);
+
+    // Nor is map 120 one of the Citadel of Trials
+    let citadel = Location::Unknown(120);
+    let frames = [
+        in_ff2(on_map(citadel)),
+        in_ff2(with_items(Location::WorldMap, &[Item::RatsTail])),
+        in_ff2(with_items(Location::WorldMap, &[Item::RatsTail])),
+    ];
+    assert_eq!(script.run(&frames), [Action::None; 0]);
+}
+
+fn party(jobs: [Job; 4]) -> Frame {
+    let party: Party = jobs
+        .into_iter()
+        .map(|job| Character {
+            name: ArrayString::new(),
+            job_id: u32::from(job),
+            level: 1,
+            exp: 0,
+            hp: 0,
+            max_hp: 0,
+            mp: 0,
+            max_mp: 0,
+        })
+        .collect();
+    Frame {
+        party,
+        ..on_map(Location::CorneliaThrone)
+    }
+}
+
+#[test]
+fn splits_the_class_change_after_the_last_member() {
+    use Job::*;
+    let mut script = new_game(BattleSplit::BattleEnd);
+
+    // Bahamut changes the job of one member at a time
+    let frames = [
+        party([Warrior, Thief, WhiteMage, BlackMage]),
+        party([Knight, Thief, WhiteMage, BlackMage]),
+        party([Knight, Ninja, WhiteMage, BlackMage]),
+        party([Knight, Ninja, WhiteWizard, BlackMage]),
+    ];
+    assert_eq!(script.run(&frames), [Action::None; 0]);
+
+    let promoted = party([Knight, Ninja, WhiteWizard, BlackWizard]);
+    assert_eq!(script.step(&promoted), Action::Split(SplitOn::ClassChange));
+    assert_eq!(script.run([&promoted, &promoted]), [Action::None; 0]);
+}
+
+#[test]
+fn loading_an_older_save_does_not_use_items() {
+    let mut script = new_game(BattleSplit::BattleEnd);
+    let nitro = [Item::Lute, Item::Nitro];
+
+    let frames = [
+        with_items(Location::WorldMap, &nitro),
+        with_items(Location::WorldMap, &nitro),
+        Frame {
+            key_items: nitro.to_vec(),
+            ..title()
+        },
+        title(),
+    ];
+    assert_eq!(script.run(&frames), [Action::None; 0]);
+
+    // The older save is from before the Nitro was picked up
+    let frames = [
+        with_items(Location::WorldMap, &[Item::Lute]),
+        with_items(Location::WorldMap, &[Item::Lute]),
+        with_items(Location::WorldMap, &[Item::Lute]),
+    ];
+    assert_eq!(script.run(&frames), [Action::Load]);
+}
+
+#[test]
+fn loading_an_older_save_does_not_trade_the_adamantite() {
+    let mut script = new_game(BattleSplit::BattleEnd);
+    // One of the upper floors of the Flying Fortress
+    let fortress = Location::Unknown(110);
+    let adamantite = [Item::Lute, Item::Adamantite];
+
+    let frames = [
+        title(),
+        with_items(fortress, &adamantite),
+        with_items(fortress, &adamantite),
+    ];
+    assert_eq!(script.run(&frames), [Action::Load]);
+
+    // The older save is from before the Adamantite was picked up
+    let frames = [
+        title(),
+        with_items(fortress, &[Item::Lute]),
+        with_items(fortress, &[Item::Lute]),
+        with_items(fortress, &[Item::Lute]),
+    ];
+    assert_eq!(script.run(&frames), [Action::Load]);
+
+    // Picked up again and handed over to the smith
+    let frames = [
+        with_items(fortress, &adamantite),
+        with_items(fortress, &adamantite),
+        with_items(fortress, &[Item::Lute]),
+        with_items(fortress, &[Item::Lute]),
+    ];
+    assert_eq!(
+        script.run(&frames),
+        [Action::Split(SplitOn::AdamantiteTrade)]
+    );
+}
+
+#[test]
+fn finds_the_rats_tail_after_leaving_an_unknown_map() {
+    let mut script = new_game(BattleSplit::BattleEnd);
+    // Not a map of the known areas, like the floors of the Citadel of Trials
+    let citadel = Location::Unknown(120);
+
+    let frames = [
+        on_map(citadel),
+        with_items(citadel, &[Item::RatsTail]),
+        with_items(citadel, &[Item::RatsTail]),
+        with_items(Location::WorldMap, &[Item::RatsTail]),
+    ];
+    assert_eq!(script.run(&frames), [Action::None; 0]);
+    assert_eq!(
+        script.step(&with_items(Location::WorldMap, &[Item::RatsTail])),
+        Action::Split(SplitOn::RatsTail)
+    );
+}
+
+/// A member of an [`EnumSet`] that is its own ordinal.
+#[derive(Debug, PartialEq)]
+struct Ordinal(u16);
+
+impl EnumSetMember for Ordinal {
+    fn ordinal(&self) -> Option<u16> {
+        Some(self.0)
+    }
+
+    fn from_ordinal(ordinal: u16) -> Option<Self> {
+        Some(Self(ordinal))
+    }
+}
+
+#[test]
+fn enum_sets_span_their_words() {
+    let mut set: EnumSet<Ordinal, 16> = EnumSet::empty();
+
+    for ordinal in [63, 64, 1023] {
+        assert!(set.insert(&Ordinal(ordinal)), "{ordinal}");
+        assert!(set.contains(&Ordinal(ordinal)), "{ordinal}");
+        assert!(set.insert(&Ordinal(ordinal)) == false, "{ordinal}");
+    }
+    assert!(set.insert(&Ordinal(1024)) == false);
+    assert!(set.contains(&Ordinal(1024)) == false);
+    assert!(set.contains(&Ordinal(65)) == false);
+
+    assert_eq!(set.len(), 3);
+    assert_eq!(
+        set.iter().collect::<Vec<_>>(),
+        [Ordinal(63), Ordinal(64), Ordinal(1023)]
+    );
+
+    assert!(set.remove(&Ordinal(64)));
+    assert!(set.remove(&Ordinal(64)) == false);
+    assert_eq!(set.len(), 2);
+    assert_eq!(set.iter().collect::<Vec<_>>(), [Ordinal(63), Ordinal(1023)]);
+}
+
+#[test]
+fn enum_sets_have_one_word_by_default() {
+    let mut set: EnumSet<Ordinal> = EnumSet::empty();
+
+    assert!(set.insert(&Ordinal(0)));
+    assert!(set.insert(&Ordinal(63)));
+    assert!(set.insert(&Ordinal(64)) == false);
+    assert_eq!(set.len(), 2);
+    assert_eq!(set.iter().collect::<Vec<_>>(), [Ordinal(0), Ordinal(63)]);
+}
+
+#[test]
+fn seeds_the_save_when_attaching_on_the_title_screen() {
+    let mut script = Script::running(BattleSplit::BattleEnd);
+    let items = [Item::Lute, Item::Crown, Item::CrystalEye];
+
+    let frames = [
+        title(),
+        title(),
+        with_items(Location::CorneliaThrone, &items),
+        with_items(Location::CorneliaThrone, &items),
+        with_items(Location::CorneliaThrone, &items),
+    ];
+    assert_eq!(script.run(&frames), [Action::Load]);
+}
+
+#[test]
+fn starting_the_next_game_of_a_marathon_does_not_reset() {
+    let mut script = new_game(BattleSplit::BattleEnd);
+    let in_ff2 = |frame: Frame| Frame {
+        game: Game::FinalFantasy2,
+        ..frame
+    };
+
+    // The managers of the next game do not exist yet
+    let booting = in_ff2(Frame::default());
+    script.next_game(&booting);
+
+    let frames = [
+        booting.clone(),
+        in_ff2(title()),
+        in_ff2(Frame {
+            fade_out: true,
+            ..title()
+        }),
+        in_ff2(on_map(Location::WorldMap)),
+    ];
+    assert_eq!(script.run(&frames), [Action::None; 0]);
+}
+
+#[test]
+fn loading_a_save_does_not_enter_the_map() {
+    let enter = Transition::Enter(Location::Cornelia);
+    let mut script = new_game(BattleSplit::BattleEnd).with_transition(enter);
+
+    let frames = [
+        on_map(Location::Cornelia),
+        title(),
+        on_map(Location::Cornelia),
+    ];
+    assert_eq!(
+        script.run(&frames),
+        [Action::Transition(enter), Action::Load]
+    );
+
+    // The selected visit was used up by walking in, not by loading the save
+    let mut script = new_game(BattleSplit::BattleEnd)
+        .with_transition(enter)
+        .with_map_occurrence(Location::Cornelia, Occurrence::Second);
+    let frames = [
+        on_map(Location::Cornelia),
+        title(),
+        on_map(Location::Cornelia),
+        on_map(Location::WorldMap),
+        on_map(Location::Cornelia),
+    ];
+    assert_eq!(
+        script.run(&frames),
+        [Action::Load, Action::Transition(enter)]
+    );
+}
+
+#[test]
+fn route_lines_do_not_enter_the_map_of_a_loaded_save() {
+    let mut script = new_game(BattleSplit::BattleEnd).with_route("enter 1\nenter 4");
+
+    // Starting the game on the world map is not entering it
+    let frames = [
+        title(),
+        on_map(Location::Cornelia),
+        on_map(Location::Cornelia),
+    ];
+    assert_eq!(script.run(&frames), [Action::Load]);
+
+    assert_eq!(
+        script.step(&on_map(Location::WorldMap)),
+        Action::Route(RouteSplit(0))
+    );
+}
@@ -0,0 +1,244 @@
+//! Recording of the game state into the log and replaying it later on.
+//!
+//! Every tick is written as a single line, but only if any of the values
+//! changed since the previous tick. A line looks like this:
+//!
+//! ```text
+//! @trace <tick> <game> <battle> <formation> <result> <time> <map> <fade> <loading> <key items> <vehicles> <party>
+//! ```
+//!
+//! The game is written like in routes, e.g. `ff1`. Flags are written as `0`
+//! or `1`, missing values as `-`, and the formation and item lists as comma
+//! separated ids. An empty formation is written as `0`, like an empty slot,
+//! so that it reads back differently from a missing one. The party is a
+//! comma separated list of `<name>:<job id>:<level>` for every member, where
+//! spaces, commas, and colons in the name are written as `_`.
+
+use asr::arrayvec::ArrayString;
+use core::fmt::{self, Write};
+
+use crate::{
+    data::{GameState, Party},
+    log,
+};
+
+const PREFIX: &str = "@trace";
+
+pub struct Recorder {
+    tick: u64,
+    last: ArrayString<512>,
+}
+
+impl Recorder {
+    pub fn new() -> Self {
+        Self {
+            tick: 0,
+            last: ArrayString::new(),
+        }
+    }
+
+    pub fn record<G: GameState>(&mut self, data: &G) {
+        let mut line = ArrayString::new();
+        if write_frame(&mut line, data).is_ok() && line != self.last {
+            log!("{PREFIX} {} {line}", self.tick);
+            self.last = line;
+        }
+        self.tick += 1;
+    }
+}
+
+pub fn write_frame<G: GameState>(out: &mut impl Write, data: &G) -> fmt::Result {
+    out.write_str(data.game().key())?;
+    write!(out, " {}", u8::from(data.battle_active()))?;
+    match data.formation() {
+        Some(formation) if formation.monsters().is_empty() => out.write_str(" 0")?,
+        Some(formation) => write_ids(out, formation.monsters().iter().map(|m| m.id()))?,
+        None => out.write_str(" -")?,
+    }
+    write!(out, " {}", u32::from(data.battle_result()))?;
+    write!(out, " {:.2}", data.battle_time())?;
+    match data.map_id() {
+        Some(map_id) => write!(out, " {map_id}")?,
+        None => out.write_str(" -")?,
+    }
+    write!(out, " {}", u8::from(data.has_fade_out()))?;
+    write!(out, " {}", u8::from(data.is_loading()))?;
+    write_ids(out, data.key_item_ids().map(u32::from))?;
+    write_ids(out, data.vehicle_ids().map(u32::from))?;
+    write_party(out, data.party())?;
+    Ok(())
+}
+
+fn write_party(out: &mut impl Write, party: &Party) -> fmt::Result {
+    let mut sep = " ";
+    for character in party {
+        out.write_str(sep)?;
+        for c in character.name.chars() {
+            match c {
+                ',' | ':' => out.write_char('_')?,
+                c if c.is_whitespace() => out.write_char('_')?,
+                c => out.write_char(c)?,
+            }
+        }
+        write!(out, ":{}:{}", character.job_id, character.level)?;
+        sep = ",";
+    }
+    if sep == " " {
+        out.write_str(" -")?;
+    }
+    Ok(())
+}
+
+fn write_ids(out: &mut impl Write, ids: impl Iterator<Item = u32>) -> fmt::Result {
+    let mut sep = " ";
+    for id in ids {
+        write!(out, "{sep}{id}")?;
+        sep = ",";
+    }
+    if sep == " " {
+        out.write_str(" -")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod replay {
+    use asr::arrayvec::ArrayString;
+    use num_enum::TryFromPrimitive;
+
+    use super::PREFIX;
+    use crate::{
+        data::{BattleResult, Character, Formation, Game, Item, Monster, Party, PARTY_SIZE},
+        script::{Frame, Script},
+        Action, BattleSplit,
+    };
+
+    /// Feeds a recorded trace through the splits and returns all actions.
+    ///
+    /// Any line that is not part of the trace is ignored, so a full log
+    /// can be passed in. Ticks that are missing because nothing changed are
+    /// filled in by repeating the previous frame.
+    pub fn replay(trace: &str, battle_split: BattleSplit) -> Vec<Action> {
+        let mut script = Script::new(battle_split);
+        let mut actions = Vec::new();
+        let mut step = |frame: &Frame| {
+            let action = script.step(frame);
+            if action != Action::None {
+                println!("{action:?}");
+                actions.push(action);
+            }
+        };
+
+        let mut last: Option<(u64, Frame)> = None;
+        for (tick, frame) in trace.lines().filter_map(parse_line) {
+            if let Some((last_tick, last_frame)) = last.take() {
+                for _ in last_tick..tick {
+                    step(&last_frame);
+                }
+            }
+            last = Some((tick, frame));
+        }
+        if let Some((_, frame)) = last {
+            step(&frame);
+        }
+
+        actions
+    }
+
+    pub fn parse_line(line: &str) -> Option<(u64, Frame)> {
+        let (_, line) = line.split_once(PREFIX)?;
+        let mut fields = line.split_whitespace();
+        let tick = fields.next()?.parse().ok()?;
+
+        let frame = Frame {
+            game: Game::from_key(fields.next()?)?,
+            battle_active: parse_flag(fields.next()?)?,
+            formation: parse_formation(fields.next()?)?,
+            battle_result: BattleResult::from(fields.next()?.parse::<u32>().ok()?),
+            battle_time: fields.next()?.parse().ok()?,
+            map_id: parse_id(fields.next()?)?,
+            fade_out: parse_flag(fields.next()?)?,
+            loading: parse_flag(fields.next()?)?,
+            key_items: parse_items(fields.next()?)?,
+            vehicles: parse_items(fields.next()?)?,
+            party: parse_party(fields.next()?)?,
+            ..Frame::default()
+        };
+
+        Some((tick, frame))
+    }
+
+    fn parse_flag(field: &str) -> Option<bool> {
+        match field {
+            "0" => Some(false),
+            "1" => Some(true),
+            _ => None,
+        }
+    }
+
+    fn parse_id(field: &str) -> Option<Option<u32>> {
+        match field {
+            "-" => Some(None),
+            id => id.parse().ok().map(Some),
+        }
+    }
+
+    fn parse_formation(field: &str) -> Option<Option<Formation>> {
+        if field == "-" {
+            return Some(None);
+        }
+        let ids = field
+            .split(',')
+            .map(|id| id.parse::<u32>().ok())
+            .collect::<Option<Vec<u32>>>()?;
+
+        // empty slots are 0, like in the game
+        let formation = ids
+            .into_iter()
+            .filter(|id| *id != 0)
+            .map(Monster::from)
+            .collect();
+        Some(Some(formation))
+    }
+
+    fn parse_items(field: &str) -> Option<Vec<Item>> {
+        if field == "-" {
+            return Some(Vec::new());
+        }
+        field
+            .split(',')
+            .map(|id| Item::try_from_primitive(id.parse().ok()?).ok())
+            .collect()
+    }
+
+    /// Only the name, job and level are recorded, the rest stays empty.
+    fn parse_party(field: &str) -> Option<Party> {
+        if field == "-" {
+            return Some(Party::new());
+        }
+        let members = field
+            .split(',')
+            .map(|member| {
+                let mut values = member.split(':');
+                let name = ArrayString::from(values.next()?).ok()?;
+                let job_id = values.next()?.parse().ok()?;
+                let level = values.next()?.parse().ok()?;
+                Some(Character {
+                    name,
+                    job_id,
+                    level,
+                    exp: 0,
+                    hp: 0,
+                    max_hp: 0,
+                    mp: 0,
+                    max_mp: 0,
+                })
+            })
+            .collect::<Option<Vec<Character>>>()?;
+
+        if members.len() > PARTY_SIZE {
+            return None;
+        }
+        Some(members.into_iter().collect())
+    }
+}
@@ -0,0 +1,260 @@
+//! Generic splits for entering or leaving any known map.
+//!
+//! The settings are not part of [`Settings`](crate::Settings), they are
+//! generated from the [`maps`] of every area, with a heading for each area.
+//! Their keys are `enter_<map id>` and `leave_<map id>`, which can also be
+//! used in the split order. Like the named location splits, every map has a
+//! `visit_<map id>` setting for which visit splits.
+
+use asr::{
+    arrayvec::ArrayString,
+    settings::{
+        self,
+        gui::{self, Widget},
+    },
+};
+use core::fmt::Write;
+
+use crate::{
+    data::{maps, Location},
+    log, EnumSet, EnumSetMember, Occurrence,
+};
+
+/// The map ids of the known areas are all below this.
+const MAPS: usize = 128;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Transition {
+    Enter(Location),
+    Leave(Location),
+}
+
+impl Transition {
+    pub fn key(self) -> ArrayString<16> {
+        let mut key = ArrayString::new();
+        let _ = match self {
+            Transition::Enter(location) => write!(key, "enter_{}", location.id()),
+            Transition::Leave(location) => write!(key, "leave_{}", location.id()),
+        };
+        key
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        let (kind, id) = key.split_once('_')?;
+        let id = id.parse::<u32>().ok()?;
+        let location = maps().find(|l| l.id() == id)?;
+        match kind {
+            "enter" => Some(Transition::Enter(location)),
+            "leave" => Some(Transition::Leave(location)),
+            _ => None,
+        }
+    }
+}
+
+/// A set of transitions, either the selected ones or the ones already split.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Transitions {
+    enter: EnumSet<Location, { MAPS / 64 }>,
+    leave: EnumSet<Location, { MAPS / 64 }>,
+}
+
+impl Transitions {
+    pub const fn empty() -> Self {
+        Self {
+            enter: EnumSet::empty(),
+            leave: EnumSet::empty(),
+        }
+    }
+
+    pub fn insert(&mut self, transition: Transition) -> bool {
+        match transition {
+            Transition::Enter(location) => self.enter.insert(&location),
+            Transition::Leave(location) => self.leave.insert(&location),
+        }
+    }
+
+    pub fn contains(&self, transition: Transition) -> bool {
+        match transition {
+            Transition::Enter(location) => self.enter.contains(&location),
+            Transition::Leave(location) => self.leave.contains(&location),
+        }
+    }
+
+    pub fn len(&self) -> u32 {
+        self.enter.len() + self.leave.len()
+    }
+}
+
+/// A small counter for every known map.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct MapCounters([u8; MAPS]);
+
+impl MapCounters {
+    const fn filled(count: u8) -> Self {
+        Self([count; MAPS])
+    }
+
+    fn get(&self, location: Location) -> u8 {
+        return location
+            .ordinal()
+            .and_then(|index| self.0.get(usize::from(index)))
+            .copied()
+            .unwrap_or(0);
+    }
+
+    fn set(&mut self, location: Location, count: u8) {
+        if let Some(counter) = location
+            .ordinal()
+            .and_then(|index| self.0.get_mut(usize::from(index)))
+        {
+            *counter = count;
+        }
+    }
+
+    fn increment(&mut self, location: Location) {
+        self.set(location, self.get(location).saturating_add(1));
+    }
+}
+
+/// How often each map was entered and left during the run.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Visits {
+    enter: MapCounters,
+    leave: MapCounters,
+}
+
+impl Visits {
+    pub const fn new() -> Self {
+        Self {
+            enter: MapCounters::filled(0),
+            leave: MapCounters::filled(0),
+        }
+    }
+
+    /// Counts moving from one map to the other.
+    pub fn count(&mut self, from: Location, to: Location) {
+        self.leave.increment(from);
+        self.enter.increment(to);
+    }
+
+    fn get(&self, transition: Transition) -> u8 {
+        match transition {
+            Transition::Enter(location) => self.enter.get(location),
+            Transition::Leave(location) => self.leave.get(location),
+        }
+    }
+}
+
+/// The selected map splits, and on which visit of the map they split.
+#[derive(PartialEq, Eq)]
+pub struct MapSplits {
+    selected: Transitions,
+    occurrences: MapCounters,
+}
+
+impl MapSplits {
+    pub const fn empty() -> Self {
+        Self {
+            selected: Transitions::empty(),
+            occurrences: MapCounters::filled(Occurrence::First.count()),
+        }
+    }
+
+    /// Adds the settings for all maps, grouped by area.
+    pub fn register() {
+        gui::add_title(
+            "_maps_heading",
+            "Map splits: split when entering or leaving a map",
+            0,
+        );
+
+        let mut area = None;
+        for location in maps() {
+            let name = location.area().map(|area| area.name);
+            if name != area {
+                area = name;
+                let mut key = ArrayString::<32>::new();
+                let _ = write!(key, "_maps_{}", location.id());
+                gui::add_title(&key, name.unwrap_or("Other"), 1);
+            }
+
+            for transition in [Transition::Enter(location), Transition::Leave(location)] {
+                let mut description = ArrayString::<64>::new();
+                let _ = match transition {
+                    Transition::Enter(_) => write!(description, "Enter {location}"),
+                    Transition::Leave(_) => write!(description, "Leave {location}"),
+                };
+                gui::add_bool(&transition.key(), &description, false);
+            }
+
+            let mut description = ArrayString::<96>::new();
+            let _ = write!(
+                description,
+                "Which time entering or leaving {location} splits"
+            );
+            Occurrence::register(&visit_key(location), &description, Default::default());
+        }
+    }
+
+    /// Reads the selected transitions from the settings again.
+    pub fn reload(&mut self) {
+        let map = settings::Map::load();
+        let mut selected = Self::empty();
+        for location in maps() {
+            for transition in [Transition::Enter(location), Transition::Leave(location)] {
+                if map
+                    .get(&transition.key())
+                    .and_then(|value| value.get_bool())
+                    .unwrap_or(false)
+                {
+                    selected.insert(transition);
+                }
+            }
+
+            let mut occurrence = Occurrence::First;
+            occurrence.update_from(&map, &visit_key(location), Default::default());
+            selected.set_occurrence(location, occurrence);
+        }
+
+        if selected != *self {
+            log!("Selected {} map splits", selected.selected.len());
+        }
+        *self = selected;
+    }
+
+    pub fn insert(&mut self, transition: Transition) -> bool {
+        self.selected.insert(transition)
+    }
+
+    /// Only splits on the given visit of the map.
+    pub fn set_occurrence(&mut self, location: Location, occurrence: Occurrence) {
+        self.occurrences.set(location, occurrence.count());
+    }
+
+    /// The selected transition for moving between the maps that was not split yet.
+    ///
+    /// Only the selected visit of a map splits, the move must already be
+    /// counted in the visits. Leaving a map comes first when both are selected.
+    pub fn find(
+        &self,
+        from: Location,
+        to: Location,
+        seen: &Transitions,
+        visits: &Visits,
+    ) -> Option<Transition> {
+        [(Transition::Leave(from), from), (Transition::Enter(to), to)]
+            .into_iter()
+            .find(|&(transition, location)| {
+                self.selected.contains(transition)
+                    && seen.contains(transition) == false
+                    && visits.get(transition) == self.occurrences.get(location)
+            })
+            .map(|(transition, _)| transition)
+    }
+}
+
+fn visit_key(location: Location) -> ArrayString<16> {
+    let mut key = ArrayString::new();
+    let _ = write!(key, "visit_{}", location.id());
+    key
+}
@@ -0,0 +1,109 @@
+//! Publishes the state of the run as timer variables, so that they can be
+//! shown with a Text component in LiveSplit.
+
+use asr::{arrayvec::ArrayString, timer};
+use core::fmt::Write;
+
+use crate::{
+    data::{BattleResult, Formation, GameState, Party, PARTY_SIZE},
+    State,
+};
+
+pub struct Variables {
+    map_id: Option<Option<u32>>,
+    formation: Option<Option<Formation>>,
+    battle_result: Option<BattleResult>,
+    seen: Option<u32>,
+    encounters: Option<(u32, u32, u32, u32)>,
+    party: Option<Party>,
+}
+
+impl Variables {
+    pub fn new() -> Self {
+        Self {
+            map_id: None,
+            formation: None,
+            battle_result: None,
+            seen: None,
+            encounters: None,
+            party: None,
+        }
+    }
+
+    /// Updates all variables that have changed since the last call.
+    pub fn update<G: GameState>(&mut self, data: &G, state: &State) {
+        let map_id = data.map_id();
+        if self.map_id.replace(map_id) != Some(map_id) {
+            // The names are only known for the mapped games
+            set("Location", |v| match (data.location(), map_id) {
+                (Some(location), _) if data.game().is_mapped() => write!(v, "{location}"),
+                (_, Some(map_id)) => write!(v, "{map_id}"),
+                (_, None) => v.write_str("-"),
+            });
+        }
+
+        let formation = data.formation();
+        if self.formation.as_ref() != Some(&formation) {
+            set("Encounter", |v| match &formation {
+                Some(formation) => write!(v, "{formation}"),
+                None => v.write_str("-"),
+            });
+            self.formation = Some(formation);
+        }
+
+        let battle_result = data.battle_result();
+        if self.battle_result.replace(battle_result) != Some(battle_result) {
+            set("Battle Result", |v| write!(v, "{battle_result:?}"));
+        }
+
+        let seen = match state {
+            State::NotRunning(_) => 0,
+            State::Running(splits) => {
+                splits.seen.len()
+                    + splits.route.len()
+                    + splits.transitions.len()
+                    + splits.used.len()
+            }
+        };
+        if self.seen.replace(seen) != Some(seen) {
+            set("Splits Seen", |v| write!(v, "{seen}"));
+        }
+
+        // Starts over with every run, like the timer
+        let encounters = match state {
+            State::NotRunning(_) => (0, 0, 0, 0),
+            State::Running(splits) => {
+                let encounters = &splits.encounters;
+                let here = data
+                    .location()
+                    .map_or(0, |location| encounters.at(location));
+                (encounters.total, here, encounters.escapes, encounters.wins)
+            }
+        };
+        if self.encounters.replace(encounters) != Some(encounters) {
+            let (total, here, escapes, wins) = encounters;
+            set("Encounters", |v| write!(v, "{total}"));
+            set("Encounters Here", |v| write!(v, "{here}"));
+            set("Escapes", |v| write!(v, "{escapes}"));
+            set("Wins", |v| write!(v, "{wins}"));
+        }
+
+        let party = data.party();
+        if self.party.as_ref() != Some(party) {
+            const KEYS: [&str; PARTY_SIZE] = ["Party 1", "Party 2", "Party 3", "Party 4"];
+            for (index, key) in KEYS.into_iter().enumerate() {
+                set(key, |v| match party.get(index) {
+                    Some(character) => write!(v, "{character}"),
+                    None => v.write_str("-"),
+                });
+            }
+            self.party = Some(party.clone());
+        }
+    }
+}
+
+fn set(key: &str, value: impl FnOnce(&mut ArrayString<128>) -> core::fmt::Result) {
+    let mut buf = ArrayString::new();
+    let _ = value(&mut buf);
+    timer::set_variable(key, &buf);
+}